@@ -1,12 +1,74 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fmt;
 use std::fs;
+use std::io::Write;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 
 fn main() -> Result<()> {
     let input = fs::read_to_string("input/day10.txt")?;
+    let args: Vec<String> = env::args().collect();
+
+    match args.get(1).map(String::as_str) {
+        Some("render") => {
+            let colour = !args.iter().any(|arg| arg == "--plain");
+            let classify_junk = args.iter().any(|arg| arg == "--classify-junk");
+            print!("{}", TileMap::new(&input)?.render(colour, classify_junk)?);
+            return Ok(());
+        }
+        Some("image") => {
+            let path = args.get(2).map(String::as_str).unwrap_or("day10.ppm");
+            TileMap::new(&input)?.write_ppm(path)?;
+            return Ok(());
+        }
+        Some("loops") => {
+            let input = match args.get(2) {
+                Some(path) => fs::read_to_string(path)?,
+                None => input,
+            };
+            let tile_map = TileMap::new(&input).or_else(|_| TileMap::parse(&input))?;
+
+            let loops = tile_map.loops();
+            for pipe_loop in &loops {
+                let ((min_x, min_y), (max_x, max_y)) = pipe_loop.bounding_box;
+                println!(
+                    "length {}, enclosed {}, box ({}, {})-({}, {})",
+                    pipe_loop.len(),
+                    pipe_loop.enclosed,
+                    min_x,
+                    min_y,
+                    max_x,
+                    max_y
+                );
+            }
+
+            if let Some(largest) = largest_loop(&loops) {
+                println!("Largest loop: {}", largest.len());
+            }
+            return Ok(());
+        }
+        Some("ascii") => {
+            let input = match args.get(2) {
+                Some(path) => fs::read_to_string(path)?,
+                None => input,
+            };
+            let alphabet = match args.iter().position(|arg| arg == "--alphabet") {
+                Some(index) => match args.get(index + 1) {
+                    Some(chars) => alphabet_from_str(chars)?,
+                    None => bail!("--alphabet expects the characters for S.|-LJ7F"),
+                },
+                None => HashMap::new(),
+            };
 
-    let part1 = part_1(&input);
-    let part2 = part_2(&input);
+            print!("{}", TileMap::parse_with(&input, &alphabet)?.to_ascii());
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let part1 = part_1(&input)?;
+    let part2 = part_2(&input)?;
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
@@ -22,6 +84,24 @@ enum Direction {
     West,
 }
 
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ];
+
+    fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 enum TileKind {
     Start,
@@ -35,19 +115,97 @@ enum TileKind {
 }
 
 impl TileKind {
-    fn from_char(c: char) -> Self {
+    const ALL: [TileKind; 8] = [
+        TileKind::Start,
+        TileKind::Ground,
+        TileKind::Vertical,
+        TileKind::Horizontal,
+        TileKind::NorthEast,
+        TileKind::NorthWest,
+        TileKind::SouthWest,
+        TileKind::SouthEast,
+    ];
+
+    fn from_char(c: char) -> Option<Self> {
         match c {
-            'S' => TileKind::Start,
-            '.' => TileKind::Ground,
-            '|' => TileKind::Vertical,
-            '-' => TileKind::Horizontal,
-            'L' => TileKind::NorthEast,
-            'J' => TileKind::NorthWest,
-            'F' => TileKind::SouthEast,
-            '7' => TileKind::SouthWest,
-            _ => unreachable!(),
+            'S' => Some(TileKind::Start),
+            '.' => Some(TileKind::Ground),
+            '|' | '│' => Some(TileKind::Vertical),
+            '-' | '─' => Some(TileKind::Horizontal),
+            'L' | '└' => Some(TileKind::NorthEast),
+            'J' | '┘' => Some(TileKind::NorthWest),
+            'F' | '┌' => Some(TileKind::SouthEast),
+            '7' | '┐' => Some(TileKind::SouthWest),
+            _ => None,
         }
     }
+
+    fn to_char(self) -> char {
+        match self {
+            TileKind::Start => 'S',
+            TileKind::Ground => '.',
+            TileKind::Vertical => '|',
+            TileKind::Horizontal => '-',
+            TileKind::NorthEast => 'L',
+            TileKind::NorthWest => 'J',
+            TileKind::SouthEast => 'F',
+            TileKind::SouthWest => '7',
+        }
+    }
+
+    fn from_connections(a: Direction, b: Direction) -> Option<Self> {
+        match (a, b) {
+            (Direction::North, Direction::South) | (Direction::South, Direction::North) => {
+                Some(TileKind::Vertical)
+            }
+            (Direction::East, Direction::West) | (Direction::West, Direction::East) => {
+                Some(TileKind::Horizontal)
+            }
+            (Direction::North, Direction::East) | (Direction::East, Direction::North) => {
+                Some(TileKind::NorthEast)
+            }
+            (Direction::North, Direction::West) | (Direction::West, Direction::North) => {
+                Some(TileKind::NorthWest)
+            }
+            (Direction::South, Direction::East) | (Direction::East, Direction::South) => {
+                Some(TileKind::SouthEast)
+            }
+            (Direction::South, Direction::West) | (Direction::West, Direction::South) => {
+                Some(TileKind::SouthWest)
+            }
+            _ => None,
+        }
+    }
+
+    fn connections(&self) -> Option<(Direction, Direction)> {
+        match self {
+            TileKind::Vertical => Some((Direction::North, Direction::South)),
+            TileKind::Horizontal => Some((Direction::East, Direction::West)),
+            TileKind::NorthEast => Some((Direction::North, Direction::East)),
+            TileKind::NorthWest => Some((Direction::North, Direction::West)),
+            TileKind::SouthEast => Some((Direction::South, Direction::East)),
+            TileKind::SouthWest => Some((Direction::South, Direction::West)),
+            TileKind::Start | TileKind::Ground => None,
+        }
+    }
+
+    fn to_box_char(self) -> char {
+        match self {
+            TileKind::Start => 'S',
+            TileKind::Ground => '.',
+            TileKind::Vertical => '│',
+            TileKind::Horizontal => '─',
+            TileKind::NorthEast => '└',
+            TileKind::NorthWest => '┘',
+            TileKind::SouthEast => '┌',
+            TileKind::SouthWest => '┐',
+        }
+    }
+
+    fn connects(&self, direction: Direction) -> bool {
+        self.connections()
+            .is_some_and(|(a, b)| a == direction || b == direction)
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -58,165 +216,519 @@ struct Tile {
 }
 
 impl Tile {
-    fn new(c: char, x: usize, y: usize) -> Self {
-        Self {
-            kind: TileKind::from_char(c),
-            x,
-            y,
-        }
+    fn new(kind: TileKind, x: usize, y: usize) -> Self {
+        Self { kind, x, y }
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 struct TileMap {
     tiles: Vec<Vec<Tile>>,
+    start: Option<(usize, usize)>,
 }
 
 impl TileMap {
-    fn new(input: &str) -> Self {
-        let tiles = input
+    fn new(input: &str) -> Result<Self> {
+        let mut tile_map = Self::parse(input)?;
+        let start_kind = tile_map.start_kind()?;
+        let (x, y) = tile_map.start.unwrap();
+        tile_map.tiles[x][y].kind = start_kind;
+
+        Ok(tile_map)
+    }
+
+    fn parse(input: &str) -> Result<Self> {
+        Self::parse_with(input, &HashMap::new())
+    }
+
+    fn parse_with(input: &str, alphabet: &HashMap<char, TileKind>) -> Result<Self> {
+        let tiles: Vec<Vec<Tile>> = input
             .lines()
             .enumerate()
             .map(|(x, line)| {
                 line.chars()
                     .enumerate()
-                    .map(|(y, c)| Tile::new(c, x, y))
+                    .map(|(y, c)| {
+                        let kind = alphabet
+                            .get(&c)
+                            .copied()
+                            .or_else(|| TileKind::from_char(c))
+                            .ok_or_else(|| anyhow!("unknown tile {:?} at ({}, {})", c, x, y))?;
+
+                        Ok(Tile::new(kind, x, y))
+                    })
                     .collect()
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
-        Self { tiles }
-    }
-
-    fn start_tile(&self) -> &Tile {
-        self.tiles
+        let start = tiles
             .iter()
             .flatten()
             .find(|tile| tile.kind == TileKind::Start)
-            .unwrap()
+            .map(|tile| (tile.x, tile.y));
+
+        Ok(Self { tiles, start })
     }
 
-    fn start_direction(&self) -> Direction {
-        let start_tile = self.start_tile();
-        let start_x = start_tile.x;
-        let start_y = start_tile.y;
+    fn to_ascii(&self) -> String {
+        let mut output = String::new();
 
-        if start_x < self.tiles.len() - 1 {
-            match self.tiles[start_x + 1][start_y].kind {
-                TileKind::Vertical | TileKind::NorthEast | TileKind::NorthWest => {
-                    return Direction::South
+        for row in &self.tiles {
+            for tile in row {
+                if Some((tile.x, tile.y)) == self.start {
+                    output.push(TileKind::Start.to_char());
+                } else {
+                    output.push(tile.kind.to_char());
                 }
-                _ => unreachable!(),
-            };
+            }
+            output.push('\n');
         }
 
-        if start_x > 0 {
-            match self.tiles[start_x - 1][start_y].kind {
-                TileKind::Vertical | TileKind::SouthEast | TileKind::SouthWest => {
-                    return Direction::North
-                }
-                _ => unreachable!(),
-            };
+        output
+    }
+
+    fn start_kind(&self) -> Result<TileKind> {
+        let start_tile = self.start_tile()?;
+        let connected = self.connections_into(start_tile);
+
+        if connected.len() != 2 {
+            bail!(
+                "start tile at ({}, {}) connects to {} pipes, expected exactly 2",
+                start_tile.x,
+                start_tile.y,
+                connected.len()
+            );
         }
 
-        if start_y > self.tiles[start_x].len() - 1 {
-            match self.tiles[start_x][start_y + 1].kind {
-                TileKind::Horizontal | TileKind::NorthWest | TileKind::SouthWest => {
-                    return Direction::East
+        Ok(TileKind::from_connections(connected[0], connected[1]).unwrap())
+    }
+
+    fn connections_into(&self, tile: &Tile) -> Vec<Direction> {
+        Direction::ALL
+            .into_iter()
+            .filter(|&direction| {
+                self.neighbour(tile, direction)
+                    .is_some_and(|next| next.kind.connects(direction.opposite()))
+            })
+            .collect()
+    }
+
+    fn connected_neighbours(&self, tile: &Tile) -> Vec<&Tile> {
+        let directions = if Some((tile.x, tile.y)) == self.start {
+            self.connections_into(tile)
+        } else {
+            match tile.kind.connections() {
+                Some((a, b)) => vec![a, b],
+                None => vec![],
+            }
+        };
+
+        directions
+            .into_iter()
+            .filter_map(|direction| {
+                self.neighbour(tile, direction)
+                    .filter(|next| next.kind.connects(direction.opposite()))
+            })
+            .collect()
+    }
+
+    fn distances(&self) -> Result<Vec<Vec<Option<usize>>>, LoopError> {
+        let start_tile = self.start_tile()?;
+        let mut distances: Vec<Vec<Option<usize>>> =
+            self.tiles.iter().map(|row| vec![None; row.len()]).collect();
+        let mut queue = VecDeque::from([start_tile]);
+        distances[start_tile.x][start_tile.y] = Some(0);
+
+        while let Some(tile) = queue.pop_front() {
+            let distance = distances[tile.x][tile.y].unwrap();
+
+            for next in self.connected_neighbours(tile) {
+                if distances[next.x][next.y].is_none() {
+                    distances[next.x][next.y] = Some(distance + 1);
+                    queue.push_back(next);
                 }
-                _ => unreachable!(),
-            };
+            }
         }
 
-        if start_y > 0 {
-            match self.tiles[start_x][start_y - 1].kind {
-                TileKind::Horizontal | TileKind::NorthEast | TileKind::SouthEast => {
-                    return Direction::West
-                }
-                _ => unreachable!(),
-            };
+        Ok(distances)
+    }
+
+    fn farthest(&self) -> Result<(usize, Vec<(usize, usize)>), LoopError> {
+        let distances = self.distances()?;
+        let max = distances
+            .iter()
+            .flatten()
+            .flatten()
+            .max()
+            .copied()
+            .unwrap_or(0);
+
+        let tiles = distances
+            .iter()
+            .enumerate()
+            .flat_map(|(x, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(move |(_, distance)| **distance == Some(max))
+                    .map(move |(y, _)| (x, y))
+            })
+            .collect();
+
+        Ok((max, tiles))
+    }
+
+    fn neighbour(&self, tile: &Tile, direction: Direction) -> Option<&Tile> {
+        let (x, y) = match direction {
+            Direction::North => (tile.x.checked_sub(1)?, tile.y),
+            Direction::South => (tile.x + 1, tile.y),
+            Direction::East => (tile.x, tile.y + 1),
+            Direction::West => (tile.x, tile.y.checked_sub(1)?),
+        };
+
+        self.tiles.get(x)?.get(y)
+    }
+
+    fn start_tile(&self) -> Result<&Tile, LoopError> {
+        let (x, y) = self.start.ok_or(LoopError::NoStart)?;
+
+        Ok(&self.tiles[x][y])
+    }
+
+    fn next_tile(
+        &self,
+        tile: &Tile,
+        direction: Direction,
+    ) -> Result<(&Tile, Direction), LoopError> {
+        let next_tile = self
+            .neighbour(tile, direction)
+            .ok_or(LoopError::EdgeOfMap {
+                x: tile.x,
+                y: tile.y,
+                direction,
+            })?;
+
+        let (a, b) = match next_tile.kind.connections() {
+            Some(connections) => connections,
+            None => {
+                return Err(LoopError::Ground {
+                    x: next_tile.x,
+                    y: next_tile.y,
+                })
+            }
+        };
+
+        let next_direction = if a == direction.opposite() {
+            b
+        } else if b == direction.opposite() {
+            a
+        } else {
+            return Err(LoopError::DisconnectedPipe {
+                x: next_tile.x,
+                y: next_tile.y,
+                direction,
+            });
+        };
+
+        Ok((next_tile, next_direction))
+    }
+
+    fn main_loop(&self) -> Result<Vec<(usize, usize)>, LoopError> {
+        self.trace_loop(self.start_tile()?)
+    }
+
+    fn trace_loop(&self, origin: &Tile) -> Result<Vec<(usize, usize)>, LoopError> {
+        let (direction, _) = origin.kind.connections().ok_or(LoopError::Ground {
+            x: origin.x,
+            y: origin.y,
+        })?;
+        let mut path = Vec::new();
+        self.trace(origin, direction, &mut path)?;
+
+        Ok(path)
+    }
+
+    // pushes every tile walked onto path, so a failed trace still reports
+    // the broken chain it followed
+    fn trace(
+        &self,
+        origin: &Tile,
+        mut direction: Direction,
+        path: &mut Vec<(usize, usize)>,
+    ) -> Result<(), LoopError> {
+        let mut tile = origin;
+        path.push((origin.x, origin.y));
+
+        loop {
+            (tile, direction) = self.next_tile(tile, direction)?;
+
+            if tile == origin {
+                break;
+            }
+
+            path.push((tile.x, tile.y));
         }
 
-        Direction::North
+        Ok(())
     }
 
-    fn next_tile(&self, tile: &Tile, direction: Direction) -> (&Tile, Direction) {
-        let current_x = tile.x;
-        let current_y = tile.y;
+    fn loops(&self) -> Vec<PipeLoop> {
+        let mut visited: Vec<Vec<bool>> = self
+            .tiles
+            .iter()
+            .map(|row| vec![false; row.len()])
+            .collect();
+        let mut loops = Vec::new();
 
-        match direction {
-            Direction::North => {
-                let next_tile = &self.tiles[current_x - 1][current_y];
-                let next_direction = match next_tile.kind {
-                    TileKind::Vertical => direction,
-                    TileKind::SouthEast => Direction::East,
-                    TileKind::SouthWest => Direction::West,
-                    TileKind::Start => Direction::North,
-                    _ => unreachable!(),
-                };
+        for tile in self.tiles.iter().flatten() {
+            let Some((forward, backward)) = tile.kind.connections() else {
+                continue;
+            };
+            if visited[tile.x][tile.y] {
+                continue;
+            }
 
-                (next_tile, next_direction)
+            // pipes only pair up with each other, so a chain that breaks
+            // never joins a loop; walking it both ways marks all of it at once
+            let mut path = Vec::new();
+            let traced = self.trace(tile, forward, &mut path);
+            if traced.is_err() {
+                let _ = self.trace(tile, backward, &mut path);
             }
-            Direction::South => {
-                let next_tile = &self.tiles[current_x + 1][current_y];
-                let next_direction = match next_tile.kind {
-                    TileKind::Vertical => direction,
-                    TileKind::NorthEast => Direction::East,
-                    TileKind::NorthWest => Direction::West,
-                    TileKind::Start => Direction::North,
-                    _ => unreachable!(),
-                };
+            for &(x, y) in &path {
+                visited[x][y] = true;
+            }
+            if traced.is_ok() {
+                loops.push(PipeLoop::new(path));
+            }
+        }
+
+        loops
+    }
+
+    fn regions(&self) -> Result<Vec<Vec<Region>>, LoopError> {
+        let mut regions: Vec<Vec<Region>> = self
+            .tiles
+            .iter()
+            .map(|row| vec![Region::Outside; row.len()])
+            .collect();
+
+        for (x, y) in self.main_loop()? {
+            regions[x][y] = Region::Loop;
+        }
 
-                (next_tile, next_direction)
+        // a tile is enclosed when a ray going east crosses the loop an odd
+        // number of times, counting only pipes that connect north
+        for (row, regions) in self.tiles.iter().zip(regions.iter_mut()) {
+            let mut inside = false;
+            for (tile, region) in row.iter().zip(regions.iter_mut()) {
+                if *region == Region::Loop {
+                    if tile.kind.connects(Direction::North) {
+                        inside = !inside;
+                    }
+                } else if inside {
+                    *region = Region::Inside;
+                }
             }
-            Direction::East => {
-                let next_tile = &self.tiles[current_x][current_y + 1];
-                let next_direction = match next_tile.kind {
-                    TileKind::Horizontal => direction,
-                    TileKind::NorthWest => Direction::North,
-                    TileKind::SouthWest => Direction::South,
-                    TileKind::Start => Direction::North,
-                    _ => unreachable!(),
+        }
+
+        Ok(regions)
+    }
+
+    // pipes off the main loop keep their box character unless classify_junk
+    // asks for them to be marked inside or outside like ground
+    fn render(&self, colour: bool, classify_junk: bool) -> Result<String, LoopError> {
+        let regions = self.regions()?;
+        let mut output = String::new();
+
+        for (row, regions) in self.tiles.iter().zip(&regions) {
+            for (tile, region) in row.iter().zip(regions) {
+                let junk = tile.kind != TileKind::Ground && !classify_junk;
+                let (c, ansi) = match region {
+                    Region::Loop => (tile.kind.to_box_char(), "\x1b[1;33m"),
+                    _ if junk => (tile.kind.to_box_char(), ""),
+                    Region::Inside => ('I', "\x1b[1;32m"),
+                    Region::Outside => ('O', "\x1b[2m"),
                 };
 
-                (next_tile, next_direction)
+                if colour && !ansi.is_empty() {
+                    output.push_str(ansi);
+                    output.push(c);
+                    output.push_str("\x1b[0m");
+                } else {
+                    output.push(c);
+                }
             }
-            Direction::West => {
-                let next_tile = &self.tiles[current_x][current_y - 1];
-                let next_direction = match next_tile.kind {
-                    TileKind::Horizontal => direction,
-                    TileKind::NorthEast => Direction::North,
-                    TileKind::SouthEast => Direction::South,
-                    TileKind::Start => Direction::North,
-                    _ => unreachable!(),
+            output.push('\n');
+        }
+
+        Ok(output)
+    }
+
+    fn write_ppm(&self, path: &str) -> Result<()> {
+        const SCALE: usize = 3;
+        const LOOP: [u8; 3] = [255, 200, 0];
+        const INSIDE: [u8; 3] = [40, 160, 60];
+        const OUTSIDE: [u8; 3] = [30, 30, 40];
+
+        let regions = self.regions()?;
+        let height = self.tiles.len() * SCALE;
+        let width = self.tiles.iter().map(Vec::len).max().unwrap_or(0) * SCALE;
+        let mut pixels = vec![OUTSIDE; width * height];
+
+        for (row, regions) in self.tiles.iter().zip(&regions) {
+            for (tile, region) in row.iter().zip(regions) {
+                let (top, left) = (tile.x * SCALE, tile.y * SCALE);
+                let mut set = |dx: usize, dy: usize, colour: [u8; 3]| {
+                    pixels[(top + dx) * width + left + dy] = colour;
                 };
 
-                (next_tile, next_direction)
+                match region {
+                    Region::Inside => {
+                        (0..SCALE * SCALE).for_each(|i| set(i / SCALE, i % SCALE, INSIDE))
+                    }
+                    Region::Outside => {}
+                    Region::Loop => {
+                        set(1, 1, LOOP);
+                        for direction in Direction::ALL {
+                            if tile.kind.connects(direction) {
+                                match direction {
+                                    Direction::North => set(0, 1, LOOP),
+                                    Direction::South => set(2, 1, LOOP),
+                                    Direction::East => set(1, 2, LOOP),
+                                    Direction::West => set(1, 0, LOOP),
+                                }
+                            }
+                        }
+                    }
+                }
             }
         }
+
+        let mut file = fs::File::create(path)?;
+        write!(file, "P6\n{} {}\n255\n", width, height)?;
+        file.write_all(&pixels.concat())?;
+
+        Ok(())
     }
 }
 
-fn part_1(input: &str) -> usize {
-    let tile_map = TileMap::new(input);
-    let mut tile = tile_map.start_tile();
-    let mut direction = tile_map.start_direction();
-    let mut sum = 0;
+fn largest_loop(loops: &[PipeLoop]) -> Option<&PipeLoop> {
+    loops.iter().max_by_key(|pipe_loop| pipe_loop.len())
+}
 
-    loop {
-        (tile, direction) = tile_map.next_tile(tile, direction);
-        sum += 1;
+#[derive(Debug, PartialEq, Clone)]
+struct PipeLoop {
+    path: Vec<(usize, usize)>,
+    enclosed: usize,
+    bounding_box: ((usize, usize), (usize, usize)),
+}
+
+impl PipeLoop {
+    fn new(path: Vec<(usize, usize)>) -> Self {
+        let enclosed = enclosed_area(&path);
+        let min_x = path.iter().map(|&(x, _)| x).min().unwrap();
+        let max_x = path.iter().map(|&(x, _)| x).max().unwrap();
+        let min_y = path.iter().map(|&(_, y)| y).min().unwrap();
+        let max_y = path.iter().map(|&(_, y)| y).max().unwrap();
+
+        Self {
+            path,
+            enclosed,
+            bounding_box: ((min_x, min_y), (max_x, max_y)),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.path.len()
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Region {
+    Loop,
+    Inside,
+    Outside,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum LoopError {
+    NoStart,
+    EdgeOfMap {
+        x: usize,
+        y: usize,
+        direction: Direction,
+    },
+    DisconnectedPipe {
+        x: usize,
+        y: usize,
+        direction: Direction,
+    },
+    Ground {
+        x: usize,
+        y: usize,
+    },
+}
 
-        if tile.kind == TileKind::Start {
-            break;
+impl fmt::Display for LoopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoopError::NoStart => write!(f, "map has no start tile"),
+            LoopError::EdgeOfMap { x, y, direction } => write!(
+                f,
+                "loop leaves the map going {:?} from ({}, {})",
+                direction, x, y
+            ),
+            LoopError::DisconnectedPipe { x, y, direction } => write!(
+                f,
+                "pipe at ({}, {}) does not connect back when entered going {:?}",
+                x, y, direction
+            ),
+            LoopError::Ground { x, y } => write!(f, "loop runs into ground at ({}, {})", x, y),
         }
     }
+}
+
+impl std::error::Error for LoopError {}
 
-    sum / 2
+fn alphabet_from_str(chars: &str) -> Result<HashMap<char, TileKind>> {
+    let chars: Vec<char> = chars.chars().collect();
+    if chars.len() != TileKind::ALL.len() {
+        bail!(
+            "alphabet needs {} characters, one for each of S.|-LJ7F",
+            TileKind::ALL.len()
+        );
+    }
+
+    Ok(chars.into_iter().zip(TileKind::ALL).collect())
 }
 
-fn part_2(input: &str) -> usize {
-    0
+fn part_1(input: &str) -> Result<usize> {
+    let tile_map = TileMap::parse(input)?;
+    let (distance, _) = tile_map.farthest()?;
+
+    Ok(distance)
+}
+
+fn part_2(input: &str) -> Result<usize> {
+    let tile_map = TileMap::new(input)?;
+    let main_loop = tile_map.main_loop()?;
+
+    Ok(enclosed_area(&main_loop))
+}
+
+fn enclosed_area(path: &[(usize, usize)]) -> usize {
+    let vertices: Vec<(i64, i64)> = path.iter().map(|&(x, y)| (x as i64, y as i64)).collect();
+
+    // shoelace formula for the area enclosed by the loop
+    let double_area = vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|((x1, y1), (x2, y2))| x1 * y2 - x2 * y1)
+        .sum::<i64>()
+        .unsigned_abs() as usize;
+
+    // pick's theorem: A = i + b/2 - 1
+    (double_area + 2 - vertices.len()) / 2
 }
 
 #[cfg(test)]
@@ -228,8 +740,8 @@ mod tests {
         let input_1 = fs::read_to_string("input/test/day10_part_1_1.txt")?;
         let input_2 = fs::read_to_string("input/test/day10_part_1_2.txt")?;
 
-        let result_1 = part_1(&input_1);
-        let result_2 = part_1(&input_2);
+        let result_1 = part_1(&input_1)?;
+        let result_2 = part_1(&input_2)?;
 
         assert_eq!(result_1, 4);
         assert_eq!(result_2, 8);
@@ -242,11 +754,206 @@ mod tests {
         let input_1 = fs::read_to_string("input/test/day10_part_2_1.txt")?;
         let input_2 = fs::read_to_string("input/test/day10_part_2_2.txt")?;
 
-        let result_1 = part_2(&input_1);
-        let result_2 = part_2(&input_2);
+        let result_1 = part_2(&input_1)?;
+        let result_2 = part_2(&input_2)?;
+
+        assert_eq!(result_1, 4);
+        assert_eq!(result_2, 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_kind() -> Result<()> {
+        let input_1 = fs::read_to_string("input/test/day10_part_1_1.txt")?;
+        let input_2 = fs::read_to_string("input/test/day10_part_1_2.txt")?;
+
+        let tile_map_1 = TileMap::new(&input_1)?;
+        let tile_map_2 = TileMap::new(&input_2)?;
+
+        assert_eq!(tile_map_1.start_tile()?.kind, TileKind::SouthEast);
+        assert_eq!(tile_map_2.start_tile()?.kind, TileKind::SouthEast);
+
+        let input_3 = ".....\n.F-7.\n.|.|.\n.L-S.\n.....";
+        assert_eq!(
+            TileMap::new(input_3)?.start_tile()?.kind,
+            TileKind::NorthWest
+        );
+        assert_eq!(part_1(input_3)?, 4);
+
+        assert!(TileMap::new(".|.\n-S-\n...").is_err());
+        assert!(TileMap::new("...\n.S-\n...").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_main_loop() -> Result<()> {
+        let input = fs::read_to_string("input/test/day10_part_1_2.txt")?;
+        let tile_map = TileMap::new(&input)?;
+        let main_loop = tile_map.main_loop()?;
+
+        assert_eq!(main_loop.len(), 16);
+        assert_eq!(&main_loop[..3], &[(2, 0), (3, 0), (4, 0)]);
+
+        let junk = "-L|F7\n7S-7|\nL|7||\n-L-J|\nL|-JF";
+        assert_eq!(TileMap::new(junk)?.main_loop()?.len(), 8);
+
+        let ground = ".S-7.\n.|.|.\n.L.J.\n.....";
+        let disconnected = ".S-7.\n.|.|.\n.L|J.\n.....";
+        let edge = "S-7\n|.|\n|.|";
+
+        assert_eq!(
+            TileMap::new(ground)?.main_loop(),
+            Err(LoopError::Ground { x: 2, y: 2 })
+        );
+        assert_eq!(
+            TileMap::new(disconnected)?.main_loop(),
+            Err(LoopError::DisconnectedPipe {
+                x: 2,
+                y: 2,
+                direction: Direction::East
+            })
+        );
+        assert_eq!(
+            TileMap::new(edge)?.main_loop(),
+            Err(LoopError::EdgeOfMap {
+                x: 2,
+                y: 0,
+                direction: Direction::South
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_farthest() -> Result<()> {
+        let input_1 = fs::read_to_string("input/test/day10_part_1_1.txt")?;
+        let input_2 = fs::read_to_string("input/test/day10_part_1_2.txt")?;
+
+        assert_eq!(TileMap::new(&input_1)?.farthest()?, (4, vec![(3, 3)]));
+        assert_eq!(TileMap::new(&input_2)?.farthest()?, (8, vec![(2, 4)]));
+
+        let distances = TileMap::new(&input_1)?.distances()?;
+        assert_eq!(distances[1][1], Some(0));
+        assert_eq!(distances[1][3], Some(2));
+        assert_eq!(distances[0][0], None);
+
+        let branches = ".....\n-S-7.\n.|.|.\n.L-J.\n.....";
+        assert!(TileMap::new(branches).is_err());
+        assert_eq!(TileMap::parse(branches)?.farthest()?, (4, vec![(3, 3)]));
+        assert_eq!(part_1(branches)?, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render() -> Result<()> {
+        let input = fs::read_to_string("input/test/day10_part_2_1.txt")?;
+        let tile_map = TileMap::new(&input)?;
+
+        let rendered = tile_map.render(false, false)?;
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[1], "O┌───────┐O");
+        assert_eq!(lines[6], "O│II│O│II│O");
+        assert_eq!(rendered.matches('I').count(), part_2(&input)?);
+        assert!(tile_map.render(true, false)?.contains("\x1b[1;33m┌\x1b[0m"));
+
+        let input = fs::read_to_string("input/test/day10_part_2_2.txt")?;
+        let rendered = TileMap::new(&input)?.render(false, true)?;
+        assert_eq!(rendered.matches('I').count(), part_2(&input)?);
+
+        // junk pipes stay visible and unhighlighted unless classified
+        let input = "S--7\n|F.|\nL--J";
+        let tile_map = TileMap::new(input)?;
+        assert_eq!(tile_map.render(false, false)?.lines().nth(1), Some("│┌I│"));
+        assert_eq!(tile_map.render(false, true)?.lines().nth(1), Some("│II│"));
+        assert!(tile_map
+            .render(true, false)?
+            .contains("│\x1b[0m┌\x1b[1;32mI"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_ppm() -> Result<()> {
+        let input = fs::read_to_string("input/test/day10_part_2_1.txt")?;
+        let path = env::temp_dir().join("aoc_2023_day10_test.ppm");
+        TileMap::new(&input)?.write_ppm(path.to_str().unwrap())?;
+
+        let image = fs::read(&path)?;
+        fs::remove_file(&path)?;
+
+        let header = b"P6\n33 27\n255\n";
+        assert!(image.starts_with(header));
+        assert_eq!(image.len(), header.len() + 33 * 27 * 3);
+
+        // the centre of the start tile is on the loop, the corner tile is not
+        let pixel = |x: usize, y: usize| {
+            let i = header.len() + (x * 33 + y) * 3;
+            image[i..i + 3].to_vec()
+        };
+        assert_eq!(pixel(4, 4), vec![255, 200, 0]);
+        assert_eq!(pixel(1, 1), vec![30, 30, 40]);
 
-        assert_eq!(result_1, 0);
-        assert_eq!(result_2, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_loops() -> Result<()> {
+        let input = "F-7.F7\n|.|.LJ\nL-J...\n..F--7\n..|..|\n..L--J";
+        let tile_map = TileMap::parse(input)?;
+        let loops = tile_map.loops();
+
+        assert_eq!(loops.len(), 3);
+        assert_eq!(
+            loops.iter().map(PipeLoop::len).collect::<Vec<_>>(),
+            vec![8, 4, 10]
+        );
+        assert_eq!(
+            loops.iter().map(|l| l.enclosed).collect::<Vec<_>>(),
+            vec![1, 0, 2]
+        );
+        assert_eq!(loops[2].bounding_box, ((3, 2), (5, 5)));
+        assert_eq!(largest_loop(&loops), Some(&loops[2]));
+        assert_eq!(tile_map.main_loop(), Err(LoopError::NoStart));
+
+        let input = fs::read_to_string("input/test/day10_part_2_2.txt")?;
+        let tile_map = TileMap::new(&input)?;
+        let loops = tile_map.loops();
+        let largest = largest_loop(&loops).unwrap();
+
+        assert_eq!(largest.path.len(), tile_map.main_loop()?.len());
+        assert_eq!(largest.enclosed, part_2(&input)?);
+
+        // long broken chains are walked once, not once per tile
+        for pipe in ["-", "|", "L"] {
+            let input = vec![pipe.repeat(1000); 200].join("\n");
+            assert!(TileMap::parse(&input)?.loops().is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_alphabets() -> Result<()> {
+        let input = fs::read_to_string("input/test/day10_part_1_2.txt")?;
+        let box_drawing = "..┌┐.\n.┌┘│.\nS┘.└┐\n│┌──┘\n└┘...\n";
+
+        let tile_map = TileMap::parse(&input)?;
+        assert_eq!(TileMap::parse(box_drawing)?, tile_map);
+        assert_eq!(TileMap::parse(box_drawing)?.to_ascii(), input);
+        assert_eq!(TileMap::new(box_drawing)?.to_ascii(), input);
+        assert_eq!(part_1(box_drawing)?, 8);
+
+        let alphabet = alphabet_from_str("*_IHABCD")?;
+        let custom = "__DC_\n_DBI_\n*B_AC\nIDHHB\nAB___\n";
+        assert_eq!(TileMap::parse_with(custom, &alphabet)?.to_ascii(), input);
+
+        assert!(alphabet_from_str("*_IH").is_err());
+        assert!(TileMap::parse("S-7\n|X|\nL-J").is_err());
 
         Ok(())
     }
@@ -1,12 +1,27 @@
+use std::env;
 use std::fs;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 
 fn main() -> Result<()> {
     let input = fs::read_to_string("input/day11.txt")?;
+    let args: Vec<String> = env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("expand") {
+        let factor = |i: usize| -> Result<usize> {
+            Ok(args
+                .get(i)
+                .ok_or_else(|| anyhow!("usage: day11 expand <rows> <columns>"))?
+                .parse()?)
+        };
+        let galaxies = galaxy_map(&input, factor(2)?, factor(3)?)?;
+        println!("Distance sum: {}", distance_sum(&galaxies));
+
+        return Ok(());
+    }
 
-    let part1 = part_1(&input);
-    let part2 = part_2(&input);
+    let part1 = part_1(&input)?;
+    let part2 = part_2(&input)?;
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
@@ -20,85 +35,87 @@ struct Galaxy {
     y: usize,
 }
 
-impl Galaxy {
-    fn distance(&self, other: &Galaxy) -> usize {
-        let x_diff = self.x.abs_diff(other.x);
-        let y_diff = self.y.abs_diff(other.y);
-
-        x_diff + y_diff
-    }
+fn part_1(input: &str) -> Result<u128> {
+    Ok(distance_sum(&galaxy_map(input, 2, 2)?))
 }
 
-fn part_1(input: &str) -> usize {
-    let galaxies = galaxy_map(input, 2);
-
-    galaxies
-        .iter()
-        .map(|galaxy| {
-            galaxies
-                .iter()
-                .fold(0, |acc, other_galaxy| acc + galaxy.distance(other_galaxy))
-        })
-        .sum::<usize>()
-        / 2
+fn part_2(input: &str) -> Result<u128> {
+    Ok(distance_sum(&galaxy_map(input, 1000000, 1000000)?))
 }
 
-fn part_2(input: &str) -> usize {
-    let galaxies = galaxy_map(input, 1000000);
+fn galaxy_map(input: &str, row_expansion: usize, column_expansion: usize) -> Result<Vec<Galaxy>> {
+    if row_expansion == 0 || column_expansion == 0 {
+        bail!("expansion factors must be at least 1");
+    }
 
-    galaxies
+    let lines: Vec<&str> = input.lines().collect();
+    let width = lines.first().map_or(0, |line| line.len());
+    if let Some((y, line)) = lines
         .iter()
-        .map(|galaxy| {
-            galaxies
-                .iter()
-                .fold(0, |acc, other_galaxy| acc + galaxy.distance(other_galaxy))
-        })
-        .sum::<usize>()
-        / 2
-}
+        .enumerate()
+        .find(|(_, line)| line.len() != width)
+    {
+        bail!(
+            "line {} is {} tiles wide, expected {} like the first line",
+            y + 1,
+            line.len(),
+            width
+        );
+    }
 
-fn galaxy_map(input: &str, expansion: usize) -> Vec<Galaxy> {
     let mut galaxies = Vec::new();
-
-    // expand verticaly
-    let mut vertical_expansion = 0;
-    for (y, line) in input.lines().enumerate() {
-        let mut expand = true;
-        for (x, c) in line.chars().enumerate() {
-            if c == '#' {
-                galaxies.push(Galaxy {
-                    x,
-                    y: y + vertical_expansion,
-                });
-                expand = false;
+    let mut row_empty = vec![true; lines.len()];
+    let mut column_empty = vec![true; width];
+    for (y, line) in lines.iter().enumerate() {
+        for (x, c) in line.bytes().enumerate() {
+            if c == b'#' {
+                galaxies.push(Galaxy { x, y });
+                row_empty[y] = false;
+                column_empty[x] = false;
             }
         }
+    }
 
-        if expand {
-            vertical_expansion += expansion - 1;
-        }
+    // shift[i] is how far index i moves once every empty line before it grows
+    let shifts = |empty: &[bool], expansion: usize| -> Vec<usize> {
+        empty
+            .iter()
+            .scan(0, |shift, &empty| {
+                let current = *shift;
+                if empty {
+                    *shift += expansion - 1;
+                }
+                Some(current)
+            })
+            .collect()
+    };
+    let row_shifts = shifts(&row_empty, row_expansion);
+    let column_shifts = shifts(&column_empty, column_expansion);
+
+    for galaxy in &mut galaxies {
+        galaxy.x += column_shifts[galaxy.x];
+        galaxy.y += row_shifts[galaxy.y];
     }
 
-    // expand horizontaly
-    let mut horizontal_expansion = 0;
-    for y in 0..input.lines().count() {
-        let mut expand = true;
-        for x in 0..input.lines().count() {
-            if input.lines().nth(x).unwrap().chars().nth(y).unwrap() == '#' {
-                expand = false;
-            }
-        }
+    Ok(galaxies)
+}
 
-        if expand {
-            galaxies
-                .iter_mut()
-                .filter(|galaxy| galaxy.x > y + horizontal_expansion)
-                .for_each(|galaxy| galaxy.x += expansion - 1);
-            horizontal_expansion += expansion - 1;
+fn distance_sum(galaxies: &[Galaxy]) -> u128 {
+    // manhattan distance splits per axis, and on sorted coordinates the ith
+    // value is the larger one in exactly i pairs
+    let axis_sum = |mut coordinates: Vec<usize>| -> u128 {
+        coordinates.sort_unstable();
+        let mut prefix: u128 = 0;
+        let mut sum: u128 = 0;
+        for (i, coordinate) in coordinates.into_iter().enumerate() {
+            sum += coordinate as u128 * i as u128 - prefix;
+            prefix += coordinate as u128;
         }
-    }
+        sum
+    };
 
-    galaxies
+    axis_sum(galaxies.iter().map(|galaxy| galaxy.x).collect())
+        + axis_sum(galaxies.iter().map(|galaxy| galaxy.y).collect())
 }
 
 #[cfg(test)]
@@ -109,7 +126,7 @@ mod tests {
     fn test_part1() -> Result<()> {
         let input = fs::read_to_string("input/test/day11.txt")?;
 
-        let result = part_1(&input);
+        let result = part_1(&input)?;
         assert_eq!(result, 374);
 
         Ok(())
@@ -119,9 +136,56 @@ mod tests {
     fn kest_part2() -> Result<()> {
         let input = fs::read_to_string("input/test/day11.txt")?;
 
-        let result = part_2(&input);
+        let result = part_2(&input)?;
         assert_eq!(result, 82000210);
 
         Ok(())
     }
+
+    #[test]
+    fn test_distance_sum() -> Result<()> {
+        let input = fs::read_to_string("input/test/day11.txt")?;
+
+        for expansion in [1, 2, 10, 100] {
+            let galaxies = galaxy_map(&input, expansion, expansion)?;
+            let brute_force: usize = galaxies
+                .iter()
+                .enumerate()
+                .flat_map(|(i, a)| {
+                    galaxies[i + 1..]
+                        .iter()
+                        .map(move |b| a.x.abs_diff(b.x) + a.y.abs_diff(b.y))
+                })
+                .sum();
+            assert_eq!(distance_sum(&galaxies), brute_force as u128);
+        }
+
+        assert_eq!(distance_sum(&galaxy_map(&input, 10, 10)?), 1030);
+        assert_eq!(distance_sum(&galaxy_map(&input, 100, 100)?), 8410);
+        assert_eq!(distance_sum(&[]), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rectangular_image() -> Result<()> {
+        let input = "#...#\n.....\n....#";
+
+        let galaxies = galaxy_map(input, 10, 2)?;
+        assert_eq!(
+            galaxies,
+            vec![
+                Galaxy { x: 0, y: 0 },
+                Galaxy { x: 7, y: 0 },
+                Galaxy { x: 7, y: 11 }
+            ]
+        );
+        assert_eq!(distance_sum(&galaxies), 36);
+        assert_eq!(distance_sum(&galaxy_map(input, 1, 1)?), 12);
+
+        assert!(galaxy_map("#...#\n...\n....#", 2, 2).is_err());
+        assert!(galaxy_map(input, 0, 2).is_err());
+
+        Ok(())
+    }
 }
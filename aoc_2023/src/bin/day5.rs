@@ -1,12 +1,59 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
 use std::fs;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 
 fn main() -> Result<()> {
     let input = fs::read_to_string("input/day5.txt")?;
+    let args: Vec<String> = env::args().collect();
 
-    let part1 = part_1(&input);
-    let part2 = part_2(&input);
+    match args.get(1).map(String::as_str) {
+        Some("segments") => {
+            let almanac = Almanac::new(&input)?;
+
+            println!("{:>12} {:>12} {:>12}", "seed", "location", "length");
+            for range in almanac.chain("seed", "location")?.segments() {
+                println!(
+                    "{:>12} {:>12} {:>12}",
+                    range.source_range_start, range.dest_range_start, range.range_length
+                );
+            }
+            return Ok(());
+        }
+        Some("inverse") => {
+            let location = args.get(2).map(|x| x.parse()).transpose()?.unwrap_or(0);
+            let almanac = Almanac::new(&input)?;
+            let chain = almanac.chain("seed", "location")?;
+
+            println!(
+                "Seeds for location {}: {:?}",
+                location,
+                chain.preimage(location)
+            );
+            println!(
+                "Lowest location in seed ranges: {}",
+                chain
+                    .lowest_image(&almanac.seed_intervals()?)
+                    .ok_or_else(|| anyhow!("no seed ranges in the almanac"))?
+            );
+            return Ok(());
+        }
+        Some("convert") => {
+            let (from, to) = match (args.get(2), args.get(3)) {
+                (Some(from), Some(to)) => (from, to),
+                _ => bail!("usage: convert <from> <to> <value>"),
+            };
+            let value = args.get(4).map(|x| x.parse()).transpose()?.unwrap_or(0);
+
+            println!("{}", Almanac::new(&input)?.convert(from, to, value)?);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let part1 = part_1(&input)?;
+    let part2 = part_2(&input)?;
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
@@ -15,37 +62,144 @@ fn main() -> Result<()> {
 }
 
 #[derive(Debug)]
+struct Almanac {
+    seeds: Vec<u64>,
+    maps: Vec<Map>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 struct Map {
+    source: String,
+    destination: String,
     ranges: Vec<Range>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone)]
 struct Range {
     dest_range_start: u64,
     source_range_start: u64,
     range_length: u64,
 }
 
+impl Almanac {
+    fn new(input: &str) -> Result<Self> {
+        let mut blocks = input.split("\n\n");
+        let seeds = blocks
+            .next()
+            .ok_or_else(|| anyhow!("almanac has no seeds line"))?
+            .trim_start_matches("seeds: ")
+            .split_whitespace()
+            .map(|s| s.parse())
+            .collect::<Result<Vec<u64>, _>>()?;
+
+        let maps = blocks.map(Map::from_block).collect::<Result<Vec<Map>>>()?;
+
+        Ok(Almanac { seeds, maps })
+    }
+
+    fn seed_intervals(&self) -> Result<Vec<(u64, u64)>> {
+        if !self.seeds.len().is_multiple_of(2) {
+            bail!(
+                "seed ranges need a start and a length, got {} values",
+                self.seeds.len()
+            );
+        }
+
+        Ok(self
+            .seeds
+            .chunks(2)
+            .map(|range| (range[0], range[0] + range[1]))
+            .collect())
+    }
+
+    fn path(&self, from: &str, to: &str) -> Result<Vec<&Map>> {
+        let mut previous: HashMap<&str, &Map> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(category) = queue.pop_front() {
+            if category == to {
+                break;
+            }
+
+            for map in self.maps.iter().filter(|map| map.source == category) {
+                if map.destination != from && !previous.contains_key(map.destination.as_str()) {
+                    previous.insert(&map.destination, map);
+                    queue.push_back(&map.destination);
+                }
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut category = to;
+        while category != from {
+            let Some(map) = previous.get(category) else {
+                bail!("no conversion from {} to {}", from, to);
+            };
+
+            path.push(*map);
+            category = &map.source;
+        }
+
+        path.reverse();
+        Ok(path)
+    }
+
+    fn chain(&self, from: &str, to: &str) -> Result<Map> {
+        Ok(self
+            .path(from, to)?
+            .into_iter()
+            .fold(Map::identity(from), |acc, map| acc.compose(map)))
+    }
+
+    fn convert(&self, from: &str, to: &str, value: u64) -> Result<u64> {
+        Ok(self
+            .path(from, to)?
+            .into_iter()
+            .fold(value, |acc, map| map.map_value(acc)))
+    }
+}
+
 impl Map {
-    fn from_block(block: &str) -> Self {
+    fn from_block(block: &str) -> Result<Self> {
+        let header = block.lines().next().unwrap_or_default();
+        let (source, destination) = header
+            .trim_end_matches(" map:")
+            .split_once("-to-")
+            .ok_or_else(|| anyhow!("invalid map header {:?}", header))?;
+
         let ranges = block
             .lines()
             .skip(1)
             .map(|line| {
-                let mut values = line.split_whitespace();
-                let dest_range_start = values.next().unwrap().parse().unwrap();
-                let source_range_start = values.next().unwrap().parse().unwrap();
-                let range_length = values.next().unwrap().parse().unwrap();
+                let values = line
+                    .split_whitespace()
+                    .map(|value| value.parse())
+                    .collect::<Result<Vec<u64>, _>>()?;
+                let [dest_range_start, source_range_start, range_length] = values[..] else {
+                    bail!("invalid range line {:?}", line);
+                };
 
-                Range {
+                Ok(Range {
                     dest_range_start,
                     source_range_start,
                     range_length,
-                }
+                })
             })
-            .collect();
+            .collect::<Result<Vec<Range>>>()?;
+
+        Ok(Map {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            ranges,
+        })
+    }
 
-        Map { ranges }
+    fn identity(category: &str) -> Self {
+        Map {
+            source: category.to_string(),
+            destination: category.to_string(),
+            ranges: Vec::new(),
+        }
     }
 
     fn map_value(&self, value: u64) -> u64 {
@@ -54,59 +208,183 @@ impl Map {
             .find(|range| {
                 let lower_bound = range.source_range_start;
                 let upper_bound = range.source_range_start + range.range_length;
-                lower_bound <= value && value <= upper_bound
+                lower_bound <= value && value < upper_bound
             })
             .map(|range| range.dest_range_start + (value - range.source_range_start))
             .unwrap_or(value)
     }
-}
 
-fn part_1(input: &str) -> u64 {
-    let (seeds, maps) = parse_input(input);
+    fn compose(&self, next: &Map) -> Self {
+        let mut ranges = Vec::new();
 
-    seeds
-        .iter()
-        .map(|seed| maps.iter().fold(*seed, |acc, map| map.map_value(acc)))
-        .min()
-        .unwrap()
-}
+        for first in self.segments() {
+            let dest_start = first.dest_range_start;
+            let dest_end = first.dest_range_start + first.range_length;
 
-fn part_2(input: &str) -> u64 {
-    let (seeds, maps) = parse_input(input);
-    let seed_ranges: Vec<&[u64]> = seeds.chunks(2).collect();
+            for second in next.segments() {
+                let source_end = second.source_range_start + second.range_length;
+                let overlap_start = dest_start.max(second.source_range_start);
+                let overlap_end = dest_end.min(source_end);
 
-    seed_ranges
-        .iter()
-        .map(|range| {
-            let mut min = std::u64::MAX;
-            for seed in range[0]..=range[0] + range[1] {
-                let mut value = seed;
-                for map in &maps {
-                    value = map.map_value(value);
+                if overlap_start >= overlap_end {
+                    continue;
                 }
 
-                min = std::cmp::min(min, value);
+                let source = first.source_range_start + (overlap_start - dest_start);
+                let dest = second.dest_range_start + (overlap_start - second.source_range_start);
+                if source != dest {
+                    ranges.push(Range {
+                        dest_range_start: dest,
+                        source_range_start: source,
+                        range_length: overlap_end - overlap_start,
+                    });
+                }
             }
+        }
 
-            min
-        })
+        ranges.sort_by_key(|range| range.source_range_start);
+        Map {
+            source: self.source.clone(),
+            destination: next.destination.clone(),
+            ranges,
+        }
+    }
+
+    fn segments(&self) -> Vec<Range> {
+        let mut ranges = self.ranges.clone();
+        ranges.sort_by_key(|range| range.source_range_start);
+
+        let mut segments = Vec::new();
+        let mut position = 0;
+
+        for range in ranges {
+            if position < range.source_range_start {
+                segments.push(Range {
+                    dest_range_start: position,
+                    source_range_start: position,
+                    range_length: range.source_range_start - position,
+                });
+            }
+
+            position = range.source_range_start + range.range_length;
+            segments.push(range);
+        }
+
+        if position < u64::MAX {
+            segments.push(Range {
+                dest_range_start: position,
+                source_range_start: position,
+                range_length: u64::MAX - position,
+            });
+        }
+
+        segments
+    }
+
+    fn preimage(&self, value: u64) -> Vec<u64> {
+        self.segments()
+            .iter()
+            .filter(|range| {
+                range.dest_range_start <= value
+                    && value - range.dest_range_start < range.range_length
+            })
+            .map(|range| range.source_range_start + (value - range.dest_range_start))
+            .collect()
+    }
+
+    fn lowest_image(&self, intervals: &[(u64, u64)]) -> Option<u64> {
+        self.segments()
+            .iter()
+            .flat_map(|range| {
+                let source_end = range.source_range_start + range.range_length;
+                intervals
+                    .iter()
+                    .filter(move |(start, end)| {
+                        *start < source_end && range.source_range_start < *end
+                    })
+                    .map(move |(start, _)| {
+                        range.dest_range_start
+                            + (start.max(&range.source_range_start) - range.source_range_start)
+                    })
+            })
+            .min()
+    }
+
+    fn map_intervals(&self, intervals: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+        let mut pending = intervals;
+        let mut mapped = Vec::new();
+
+        for range in &self.ranges {
+            let source_start = range.source_range_start;
+            let source_end = range.source_range_start + range.range_length;
+            let mut unmapped = Vec::new();
+
+            for (start, end) in pending {
+                let overlap_start = start.max(source_start);
+                let overlap_end = end.min(source_end);
+
+                if overlap_start < overlap_end {
+                    mapped.push((
+                        range.dest_range_start + (overlap_start - source_start),
+                        range.dest_range_start + (overlap_end - source_start),
+                    ));
+                }
+
+                let before_end = end.min(source_start);
+                if start < before_end {
+                    unmapped.push((start, before_end));
+                }
+
+                let after_start = start.max(source_end);
+                if after_start < end {
+                    unmapped.push((after_start, end));
+                }
+            }
+
+            pending = unmapped;
+        }
+
+        mapped.extend(pending);
+        mapped
+    }
+}
+
+fn part_1(input: &str) -> Result<u64> {
+    let almanac = Almanac::new(input)?;
+    let chain = almanac.chain("seed", "location")?;
+
+    almanac
+        .seeds
+        .iter()
+        .map(|seed| chain.map_value(*seed))
         .min()
-        .unwrap()
+        .ok_or_else(|| anyhow!("no seeds in the almanac"))
 }
 
-fn parse_input(input: &str) -> (Vec<u64>, Vec<Map>) {
-    let mut blocks = input.split("\n\n");
-    let seeds = blocks
-        .next()
-        .unwrap()
-        .trim_start_matches("seeds: ")
-        .split_whitespace()
-        .map(|s| s.parse().unwrap())
-        .collect();
+fn part_2(input: &str) -> Result<u64> {
+    let almanac = Almanac::new(input)?;
+    let chain = almanac.chain("seed", "location")?;
+
+    chain
+        .map_intervals(almanac.seed_intervals()?)
+        .iter()
+        .map(|(start, _)| *start)
+        .min()
+        .ok_or_else(|| anyhow!("no seed ranges in the almanac"))
+}
 
-    let maps = blocks.map(Map::from_block).collect();
+#[cfg(test)]
+fn part_2_brute_force(input: &str) -> Result<u64> {
+    let almanac = Almanac::new(input)?;
+    let chain = almanac.chain("seed", "location")?;
 
-    (seeds, maps)
+    almanac
+        .seed_intervals()?
+        .into_iter()
+        .flat_map(|(start, end)| start..end)
+        .map(|seed| chain.map_value(seed))
+        .min()
+        .ok_or_else(|| anyhow!("no seed ranges in the almanac"))
 }
 
 #[cfg(test)]
@@ -117,7 +395,7 @@ mod tests {
     fn test_part_1() -> Result<()> {
         let input = fs::read_to_string("input/test/day5.txt")?;
 
-        let result = part_1(&input);
+        let result = part_1(&input)?;
         assert_eq!(result, 35);
 
         Ok(())
@@ -127,9 +405,98 @@ mod tests {
     fn test_part_2() -> Result<()> {
         let input = fs::read_to_string("input/test/day5.txt")?;
 
-        let result = part_2(&input);
+        let result = part_2(&input)?;
         assert_eq!(result, 46);
 
         Ok(())
     }
+
+    #[test]
+    fn test_map_intervals() -> Result<()> {
+        let input = fs::read_to_string("input/test/day5.txt")?;
+        let almanac = Almanac::new(&input)?;
+
+        let mut intervals = almanac.maps[0].map_intervals(vec![(45, 105)]);
+        intervals.sort();
+        assert_eq!(intervals, vec![(45, 50), (50, 52), (52, 100), (100, 105)]);
+
+        assert_eq!(part_2(&input)?, part_2_brute_force(&input)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compose() -> Result<()> {
+        let input = fs::read_to_string("input/test/day5.txt")?;
+        let almanac = Almanac::new(&input)?;
+        let chain = almanac.chain("seed", "location")?;
+
+        for seed in 0..120 {
+            assert_eq!(
+                chain.map_value(seed),
+                almanac.convert("seed", "location", seed)?
+            );
+        }
+
+        let segments = chain.segments();
+        assert_eq!(segments[0].source_range_start, 0);
+        assert!(segments
+            .windows(2)
+            .all(|w| w[0].source_range_start + w[0].range_length == w[1].source_range_start));
+
+        assert_eq!(chain.preimage(35), vec![13]);
+        assert!(chain.preimage(82).contains(&79));
+
+        assert_eq!(chain.lowest_image(&almanac.seed_intervals()?), Some(46));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_value_bounds() -> Result<()> {
+        let input = fs::read_to_string("input/test/day5.txt")?;
+        let almanac = Almanac::new(&input)?;
+        let seed_to_soil = &almanac.maps[0];
+
+        assert_eq!(seed_to_soil.map_value(49), 49);
+        assert_eq!(seed_to_soil.map_value(50), 52);
+        assert_eq!(seed_to_soil.map_value(97), 99);
+        assert_eq!(seed_to_soil.map_value(98), 50);
+        assert_eq!(seed_to_soil.map_value(99), 51);
+        assert_eq!(seed_to_soil.map_value(100), 100);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_almanac_categories() -> Result<()> {
+        let input = fs::read_to_string("input/test/day5.txt")?;
+        let almanac = Almanac::new(&input)?;
+
+        assert_eq!(almanac.maps[0].source, "seed");
+        assert_eq!(almanac.maps[0].destination, "soil");
+        assert_eq!(almanac.convert("seed", "soil", 79)?, 81);
+        assert_eq!(almanac.convert("soil", "humidity", 81)?, 78);
+        assert_eq!(almanac.convert("seed", "location", 79)?, 82);
+        assert_eq!(almanac.convert("light", "light", 7)?, 7);
+        assert!(almanac.convert("location", "seed", 82).is_err());
+        assert!(almanac.convert("seed", "planet", 79).is_err());
+
+        let mut blocks: Vec<&str> = input.trim_end().split("\n\n").collect();
+        blocks[1..].reverse();
+        let shuffled = blocks.join("\n\n");
+        assert_eq!(part_1(&shuffled)?, 35);
+        assert_eq!(part_2(&shuffled)?, 46);
+
+        // a missing category or a malformed block is an error, not a panic
+        let no_location = blocks[..blocks.len() - 1].join("\n\n");
+        assert!(Almanac::new(&no_location).is_ok());
+        assert!(part_1(&no_location).is_err());
+        assert!(part_2(&no_location).is_err());
+        assert!(Almanac::new("seeds: 1 2\n\nseed to soil map:\n1 2 3").is_err());
+        assert!(Almanac::new("seeds: 1 2\n\nseed-to-soil map:\n1 2").is_err());
+        assert!(part_2("seeds: 1 2 3\n\nseed-to-location map:\n1 2 3").is_err());
+
+        Ok(())
+    }
 }
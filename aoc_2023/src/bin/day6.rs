@@ -1,12 +1,27 @@
+use std::env;
+use std::fmt;
 use std::fs;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 fn main() -> Result<()> {
     let input = fs::read_to_string("input/day6.txt")?;
 
-    let part1 = part_1(&input);
-    let part2 = part_2(&input);
+    if env::args().nth(1).as_deref() == Some("table") {
+        let (race_durations, record_distances) = parse_input(&input);
+        let (race_duration, record_distance) = concatenated_race(&input);
+
+        println!("{}", RaceAnalysis::header());
+        for (race_duration, record_distance) in race_durations.iter().zip(record_distances) {
+            println!("{}", RaceAnalysis::new(*race_duration, record_distance)?);
+        }
+        println!("{}", RaceAnalysis::new(race_duration, record_distance)?);
+
+        return Ok(());
+    }
+
+    let part1 = part_1(&input)?;
+    let part2 = part_2(&input)?;
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
@@ -14,20 +29,125 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn part_1(input: &str) -> u64 {
-    let (race_durations, record_distances) = parse_input(input);
-    let mut possible_wins = vec![0; race_durations.len()];
+#[derive(Debug, PartialEq)]
+struct RaceAnalysis {
+    race_duration: u128,
+    record_distance: u128,
+    winning_window: Option<(u128, u128)>,
+    optimal_hold: u128,
+    optimal_distance: u128,
+}
+
+impl RaceAnalysis {
+    fn header() -> String {
+        format!(
+            "{:>10} {:>16} {:>10} {:>10} {:>10} {:>10} {:>16} {:>16}",
+            "time",
+            "record",
+            "first win",
+            "last win",
+            "wins",
+            "best hold",
+            "best distance",
+            "margin"
+        )
+    }
+
+    fn new(race_duration: u128, record_distance: u128) -> Result<Self> {
+        let optimal_hold = race_duration / 2;
+        let optimal_distance = optimal_hold
+            .checked_mul(race_duration - optimal_hold)
+            .ok_or_else(|| anyhow!("distances for a {} ms race overflow a u128", race_duration))?;
 
-    for (index, (race_duration, record_distance)) in
-        race_durations.iter().zip(record_distances).enumerate()
-    {
-        possible_wins[index] = calculate_possible_wins(*race_duration, record_distance);
+        Ok(Self {
+            race_duration,
+            record_distance,
+            winning_window: Self::winning_window(race_duration, record_distance)?,
+            optimal_hold,
+            optimal_distance,
+        })
     }
 
-    possible_wins.iter().product::<u64>()
+    fn winning_window(race_duration: u128, record_distance: u128) -> Result<Option<(u128, u128)>> {
+        // with T = 2m + r and h = m - k, h * (T - h) = m (m + r) - k (k + r),
+        // so h wins exactly when k (k + r) < m (m + r) - D, which keeps every
+        // intermediate below the best distance instead of T²
+        let half = race_duration / 2;
+        let odd = race_duration % 2;
+        let best = half
+            .checked_mul(half + odd)
+            .ok_or_else(|| anyhow!("distances for a {} ms race overflow a u128", race_duration))?;
+
+        let Some(slack) = best.checked_sub(record_distance).filter(|slack| *slack > 0) else {
+            return Ok(None);
+        };
+
+        // largest k with k (k + r) <= slack - 1; isqrt is exact for even races
+        // and at most one too large for odd ones
+        let mut k = (slack - 1).isqrt();
+        if k * (k + odd) > slack - 1 {
+            k -= 1;
+        }
+
+        let left_index = half - k;
+        Ok(Some((left_index, race_duration - left_index)))
+    }
+
+    fn possible_wins(&self) -> u128 {
+        self.winning_window
+            .map_or(0, |(left_index, right_index)| right_index - left_index + 1)
+    }
+
+    fn margin(&self) -> Option<u128> {
+        self.winning_window
+            .map(|_| self.optimal_distance - self.record_distance)
+    }
 }
 
-fn part_2(input: &str) -> u64 {
+impl fmt::Display for RaceAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (first, last) = match self.winning_window {
+            Some((first, last)) => (first.to_string(), last.to_string()),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        let margin = self.margin().map_or("-".to_string(), |m| m.to_string());
+
+        write!(
+            f,
+            "{:>10} {:>16} {:>10} {:>10} {:>10} {:>10} {:>16} {:>16}",
+            self.race_duration,
+            self.record_distance,
+            first,
+            last,
+            self.possible_wins(),
+            self.optimal_hold,
+            self.optimal_distance,
+            margin
+        )
+    }
+}
+
+fn part_1(input: &str) -> Result<u128> {
+    let (race_durations, record_distances) = parse_input(input);
+
+    race_durations.iter().zip(record_distances).try_fold(
+        1u128,
+        |product, (race_duration, record_distance)| {
+            let wins = RaceAnalysis::new(*race_duration, record_distance)?.possible_wins();
+            product
+                .checked_mul(wins)
+                .ok_or_else(|| anyhow!("product of possible wins overflows a u128"))
+        },
+    )
+}
+
+fn part_2(input: &str) -> Result<u128> {
+    let (race_duration, record_distance) = concatenated_race(input);
+
+    Ok(RaceAnalysis::new(race_duration, record_distance)?.possible_wins())
+}
+
+fn concatenated_race(input: &str) -> (u128, u128) {
     let (race_durations, record_distances) = parse_input(input);
 
     let race_duration = race_durations
@@ -40,7 +160,7 @@ fn part_2(input: &str) -> u64 {
         .parse()
         .unwrap();
 
-    let record_distance: u64 = record_distances
+    let record_distance = record_distances
         .iter()
         .map(|x| x.to_string())
         .fold(String::from(""), |mut acc, x| {
@@ -50,24 +170,11 @@ fn part_2(input: &str) -> u64 {
         .parse()
         .unwrap();
 
-    calculate_possible_wins(race_duration, record_distance)
+    (race_duration, record_distance)
 }
 
-fn calculate_possible_wins(race_duration: u64, record_distance: u64) -> u64 {
-    let left_index = (0..race_duration)
-        .find(|i| i * (race_duration - i) > record_distance)
-        .unwrap();
-
-    let right_index = (0..race_duration)
-        .rev()
-        .find(|i| i * (race_duration - i) > record_distance)
-        .unwrap();
-
-    right_index - left_index + 1
-}
-
-fn parse_input(input: &str) -> (Vec<u64>, Vec<u64>) {
-    let race_durations: Vec<u64> = input
+fn parse_input(input: &str) -> (Vec<u128>, Vec<u128>) {
+    let race_durations: Vec<u128> = input
         .lines()
         .next()
         .unwrap()
@@ -76,7 +183,7 @@ fn parse_input(input: &str) -> (Vec<u64>, Vec<u64>) {
         .map(|x| x.parse().unwrap())
         .collect();
 
-    let record_distances: Vec<u64> = input
+    let record_distances: Vec<u128> = input
         .lines()
         .nth(1)
         .unwrap()
@@ -96,7 +203,7 @@ mod tests {
     fn test_part_1() -> Result<()> {
         let input = fs::read_to_string("input/test/day6.txt")?;
 
-        let result = part_1(&input);
+        let result = part_1(&input)?;
         assert_eq!(result, 288);
 
         Ok(())
@@ -106,9 +213,67 @@ mod tests {
     fn test_part_2() -> Result<()> {
         let input = fs::read_to_string("input/test/day6.txt")?;
 
-        let result = part_2(&input);
+        let result = part_2(&input)?;
         assert_eq!(result, 71503);
 
         Ok(())
     }
+
+    #[test]
+    fn test_possible_wins() -> Result<()> {
+        for race_duration in 0..60 {
+            for record_distance in 0..(race_duration * race_duration / 4 + 3) {
+                let expected = (0..=race_duration)
+                    .filter(|hold| hold * (race_duration - hold) > record_distance)
+                    .count() as u128;
+
+                assert_eq!(
+                    RaceAnalysis::new(race_duration, record_distance)?.possible_wins(),
+                    expected
+                );
+            }
+        }
+
+        let race_duration = 4_000_000_000_000_000_000;
+        let record_distance = race_duration * race_duration / 4 - 1;
+        assert_eq!(
+            RaceAnalysis::new(race_duration, record_distance)?.possible_wins(),
+            1
+        );
+
+        // races longer than 2^64 ms, where T² no longer fits in a u128
+        let half = (1u128 << 63) + 1;
+        let analysis = RaceAnalysis::new(2 * half, half * half - 1)?;
+        assert_eq!(analysis.winning_window, Some((half, half)));
+        assert_eq!(analysis.optimal_distance, half * half);
+
+        let analysis = RaceAnalysis::new(2 * half + 1, half * (half + 1) - 3)?;
+        assert_eq!(analysis.winning_window, Some((half - 1, half + 2)));
+        assert_eq!(analysis.possible_wins(), 4);
+
+        assert!(RaceAnalysis::new(1u128 << 66, 0).is_err());
+        assert!(RaceAnalysis::new(u128::MAX, u128::MAX).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_race_analysis() -> Result<()> {
+        let analysis = RaceAnalysis::new(7, 9)?;
+
+        assert_eq!(analysis.winning_window, Some((2, 5)));
+        assert_eq!(analysis.optimal_hold, 3);
+        assert_eq!(analysis.optimal_distance, 12);
+        assert_eq!(analysis.margin(), Some(3));
+
+        let analysis = RaceAnalysis::new(6, 9)?;
+
+        assert_eq!(analysis.winning_window, None);
+        assert_eq!(analysis.possible_wins(), 0);
+        assert_eq!(analysis.optimal_distance, 9);
+        assert_eq!(analysis.margin(), None);
+        assert!(analysis.to_string().ends_with("-"));
+
+        Ok(())
+    }
 }
@@ -1,14 +1,53 @@
-use std::cmp::Ordering;
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::env;
 use std::fs;
+use std::time::Instant;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 
 fn main() -> Result<()> {
     let input = fs::read_to_string("input/day7.txt")?;
+    let args: Vec<String> = env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("table") {
+        let rules = match args.get(2).map(String::as_str) {
+            Some("jokers") => Rules::jokers(),
+            _ => Rules::standard(),
+        };
+
+        println!(
+            "{:>5} {:>6} {:>16} {:>6} {:>6} {:>12}",
+            "rank", "hand", "category", "wild", "bid", "winnings"
+        );
+        for (i, (evaluation, bid)) in ranked_hands(&input, &rules)?.iter().enumerate() {
+            println!(
+                "{:>5} {:>6} {:>16} {:>6} {:>6} {:>12}",
+                i + 1,
+                evaluation.cards,
+                rules.categories[evaluation.hand.category].0,
+                evaluation.wild_as.map_or("-".to_string(), String::from),
+                bid,
+                winnings(i, *bid)?
+            );
+        }
+
+        return Ok(());
+    }
+
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let count = args
+            .get(2)
+            .map(|x| x.parse())
+            .transpose()?
+            .unwrap_or(1_000_000);
+        benchmark(count)?;
 
-    let part1 = part_1(&input);
-    let part2 = part_2(&input);
+        return Ok(());
+    }
+
+    let part1 = part_1(&input)?;
+    let part2 = part_2(&input)?;
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
@@ -16,227 +55,432 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-#[derive(Debug, PartialEq)]
-enum Card {
-    A,
-    K,
-    Q,
-    J(bool),
-    T,
-    N(u8),
+#[derive(Debug, PartialEq, Clone)]
+struct Rules {
+    card_order: Vec<char>,
+    wild_cards: Vec<char>,
+    hand_size: usize,
+    categories: Vec<(&'static str, Vec<usize>)>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 struct Hand {
-    cards: Vec<Card>,
+    category: usize,
+    ranks: Vec<usize>,
 }
 
-#[derive(Debug, PartialEq)]
-enum HandType {
-    FiveOfAKind(Hand),
-    FourOfAKind(Hand),
-    FullHouse(Hand),
-    ThreeOfAKind(Hand),
-    TwoPair(Hand),
-    OnePair(Hand),
-    HighCard(Hand),
+#[derive(Debug, PartialEq, Clone)]
+struct Evaluation {
+    cards: String,
+    hand: Hand,
+    counts: Vec<(char, usize)>,
+    wild_as: Option<char>,
+    sort_key: Option<u64>,
 }
 
-impl Card {
-    fn from_char(c: char, joker: bool) -> Self {
-        match c {
-            'A' => Card::A,
-            'K' => Card::K,
-            'Q' => Card::Q,
-            'J' => Card::J(joker),
-            'T' => Card::T,
-            _ => Card::N(c.to_digit(10).unwrap() as u8),
+impl Rules {
+    fn standard() -> Self {
+        Rules {
+            card_order: "23456789TJQKA".chars().collect(),
+            wild_cards: Vec::new(),
+            hand_size: 5,
+            categories: vec![
+                ("high card", vec![1, 1, 1, 1, 1]),
+                ("one pair", vec![2, 1, 1, 1]),
+                ("two pair", vec![2, 2, 1]),
+                ("three of a kind", vec![3, 1, 1]),
+                ("full house", vec![3, 2]),
+                ("four of a kind", vec![4, 1]),
+                ("five of a kind", vec![5]),
+            ],
         }
     }
-}
 
-impl Hand {
-    fn from_str(hand: &str, joker: bool) -> Self {
-        Hand {
-            cards: hand.chars().map(|x| Card::from_char(x, joker)).collect(),
+    fn jokers() -> Self {
+        Rules {
+            card_order: "J23456789TQKA".chars().collect(),
+            wild_cards: vec!['J'],
+            ..Rules::standard()
         }
     }
-}
 
-impl PartialOrd for Card {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (Card::A, Card::A) => Some(Ordering::Equal),
-            (Card::A, _) => Some(Ordering::Greater),
-            (_, Card::A) => Some(Ordering::Less),
+    fn evaluate(&self, hand: &str) -> Result<Evaluation> {
+        let cards: Vec<char> = hand.chars().collect();
+        if cards.len() != self.hand_size {
+            bail!(
+                "hand {:?} has {} cards, expected {}",
+                hand,
+                cards.len(),
+                self.hand_size
+            );
+        }
+
+        let ranks = cards
+            .iter()
+            .map(|card| self.rank(*card))
+            .collect::<Result<Vec<usize>>>()?;
 
-            (Card::K, Card::K) => Some(Ordering::Equal),
-            (Card::K, _) => Some(Ordering::Greater),
-            (_, Card::K) => Some(Ordering::Less),
+        let mut map = HashMap::new();
+        for card in &cards {
+            map.entry(*card).and_modify(|e| *e += 1).or_insert(1);
+        }
 
-            (Card::Q, Card::Q) => Some(Ordering::Equal),
-            (Card::Q, _) => Some(Ordering::Greater),
-            (_, Card::Q) => Some(Ordering::Less),
+        let mut counts: Vec<(char, usize)> = map.into_iter().collect();
+        counts.sort_unstable_by_key(|(card, count)| {
+            (Reverse(*count), Reverse(self.rank(*card).unwrap()))
+        });
+
+        // wild cards always join the largest group, preferring the strongest card
+        let wilds: usize = counts
+            .iter()
+            .filter(|(card, _)| self.wild_cards.contains(card))
+            .map(|(_, count)| count)
+            .sum();
+        let wild_as = if wilds == 0 {
+            None
+        } else {
+            counts
+                .iter()
+                .find(|(card, _)| !self.wild_cards.contains(card))
+                .map(|(card, _)| *card)
+                .or(self.card_order.last().copied())
+        };
+
+        let mut group_sizes: Vec<usize> = counts
+            .iter()
+            .filter(|(card, _)| !self.wild_cards.contains(card))
+            .map(|(_, count)| *count)
+            .collect();
+        match group_sizes.first_mut() {
+            Some(count) => *count += wilds,
+            None => group_sizes.push(wilds),
+        }
 
-            (Card::J(false), _) => Some(Ordering::Greater),
-            (_, Card::J(false)) => Some(Ordering::Less),
-            (Card::J(true), _) => Some(Ordering::Less),
+        let category = self
+            .categories
+            .iter()
+            .position(|(_, category)| *category == group_sizes)
+            .ok_or_else(|| anyhow!("no hand category for card counts {:?}", group_sizes))?;
+
+        let sort_key = self.sort_key(category, &ranks);
+
+        Ok(Evaluation {
+            cards: hand.to_string(),
+            hand: Hand { category, ranks },
+            counts,
+            wild_as,
+            sort_key,
+        })
+    }
 
-            (Card::T, Card::T) => Some(Ordering::Equal),
-            (Card::T, _) => Some(Ordering::Greater),
-            (_, Card::T) => Some(Ordering::Less),
+    // packs the category above the card ranks so a single integer compare
+    // orders hands the same way as `Hand`'s `Ord`, when they fit in 64 bits
+    fn sort_key(&self, category: usize, ranks: &[usize]) -> Option<u64> {
+        let bits = |n: usize| usize::BITS - n.saturating_sub(1).leading_zeros();
+        let rank_bits = bits(self.card_order.len());
+        let category_bits = bits(self.categories.len());
 
-            (Card::N(a), Card::N(b)) => a.partial_cmp(b),
-            (Card::N(_), _) => Some(Ordering::Greater),
+        if category_bits + rank_bits * self.hand_size as u32 > u64::BITS {
+            return None;
         }
+
+        Some(ranks.iter().fold(category as u64, |key, rank| {
+            (key << rank_bits) | *rank as u64
+        }))
+    }
+
+    fn rank(&self, card: char) -> Result<usize> {
+        self.card_order
+            .iter()
+            .position(|c| *c == card)
+            .ok_or_else(|| anyhow!("unknown card {:?}", card))
     }
 }
 
-impl HandType {
-    fn from_hand(hand: &str, joker: bool) -> Self {
-        let mut map = HashMap::new();
+fn ranked_hands(input: &str, rules: &Rules) -> Result<Vec<(Evaluation, u64)>> {
+    let mut hands: Vec<(Evaluation, u64)> = Vec::new();
 
-        if joker {
-            let mut jokers = 0;
-            for card in hand.chars() {
-                if card == 'J' {
-                    jokers += 1;
-                    continue;
-                }
-                map.entry(card).and_modify(|e| *e += 1).or_insert(1);
-            }
+    for line in input.lines() {
+        let hand = line.split_whitespace().next().unwrap();
+        let bid = line.split_whitespace().nth(1).unwrap();
 
-            if jokers == 5 {
-                return HandType::FiveOfAKind(Hand::from_str(hand, joker));
-            }
+        hands.push((rules.evaluate(hand)?, bid.parse()?));
+    }
 
-            let max = map
-                .iter()
-                .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-                .unwrap()
-                .0;
+    if hands
+        .iter()
+        .all(|(evaluation, _)| evaluation.sort_key.is_some())
+    {
+        hands.sort_unstable_by_key(|(evaluation, _)| evaluation.sort_key);
+    } else {
+        hands.sort_unstable_by(|(a, _), (b, _)| a.hand.cmp(&b.hand));
+    }
+    Ok(hands)
+}
 
-            map.entry(*max)
-                .and_modify(|e| *e += jokers)
-                .or_insert(jokers);
-        } else {
-            for card in hand.chars() {
-                map.entry(card).and_modify(|e| *e += 1).or_insert(1);
-            }
-        }
+fn winnings(index: usize, bid: u64) -> Result<u64> {
+    (index as u64 + 1)
+        .checked_mul(bid)
+        .ok_or_else(|| anyhow!("winnings for rank {} overflow a u64", index + 1))
+}
 
-        match map.len() {
-            1 => HandType::FiveOfAKind(Hand::from_str(hand, joker)),
-            2 => {
-                if map.values().any(|&v| v == 4) {
-                    HandType::FourOfAKind(Hand::from_str(hand, joker))
-                } else {
-                    HandType::FullHouse(Hand::from_str(hand, joker))
-                }
-            }
-            3 => {
-                if map.values().any(|&v| v == 3) {
-                    HandType::ThreeOfAKind(Hand::from_str(hand, joker))
-                } else {
-                    HandType::TwoPair(Hand::from_str(hand, joker))
-                }
-            }
-            4 => HandType::OnePair(Hand::from_str(hand, joker)),
-            _ => HandType::HighCard(Hand::from_str(hand, joker)),
-        }
-    }
+fn total_winnings(input: &str, rules: &Rules) -> Result<u64> {
+    ranked_hands(input, rules)?
+        .iter()
+        .enumerate()
+        .try_fold(0u64, |acc, (i, (_, bid))| {
+            acc.checked_add(winnings(i, *bid)?)
+                .ok_or_else(|| anyhow!("total winnings overflow a u64"))
+        })
 }
 
-impl PartialOrd for HandType {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (HandType::FiveOfAKind(self_hand), HandType::FiveOfAKind(other_hand)) => {
-                compare_eq_hand(self_hand, other_hand)
-            }
-            (HandType::FiveOfAKind(_), _) => Some(Ordering::Greater),
-            (_, HandType::FiveOfAKind(_)) => Some(Ordering::Less),
+fn part_1(input: &str) -> Result<u64> {
+    total_winnings(input, &Rules::standard())
+}
 
-            (HandType::FourOfAKind(self_hand), HandType::FourOfAKind(other_hand)) => {
-                compare_eq_hand(self_hand, other_hand)
-            }
-            (HandType::FourOfAKind(_), _) => Some(Ordering::Greater),
-            (_, HandType::FourOfAKind(_)) => Some(Ordering::Less),
+fn part_2(input: &str) -> Result<u64> {
+    total_winnings(input, &Rules::jokers())
+}
 
-            (HandType::FullHouse(self_hand), HandType::FullHouse(other_hand)) => {
-                compare_eq_hand(self_hand, other_hand)
-            }
-            (HandType::FullHouse(_), _) => Some(Ordering::Greater),
-            (_, HandType::FullHouse(_)) => Some(Ordering::Less),
+fn benchmark(count: usize) -> Result<()> {
+    let rules = Rules::standard();
+    let mut seed: u64 = 0x2545f4914f6cdd1d;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    let hands: Vec<String> = (0..count)
+        .map(|_| {
+            (0..rules.hand_size)
+                .map(|_| rules.card_order[next() as usize % rules.card_order.len()])
+                .collect()
+        })
+        .collect();
+    let evaluations = hands
+        .iter()
+        .map(|hand| rules.evaluate(hand))
+        .collect::<Result<Vec<Evaluation>>>()?;
 
-            (HandType::ThreeOfAKind(self_hand), HandType::ThreeOfAKind(other_hand)) => {
-                compare_eq_hand(self_hand, other_hand)
-            }
-            (HandType::ThreeOfAKind(_), _) => Some(Ordering::Greater),
-            (_, HandType::ThreeOfAKind(_)) => Some(Ordering::Less),
+    let mut by_hand_type: Vec<(legacy::HandType, usize)> = hands
+        .iter()
+        .enumerate()
+        .map(|(i, hand)| (legacy::HandType::from_hand(hand, false), i))
+        .collect();
+    let start = Instant::now();
+    by_hand_type.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let hand_type_time = start.elapsed();
+
+    let mut by_hand = evaluations.clone();
+    let start = Instant::now();
+    by_hand.sort_unstable_by(|a, b| a.hand.cmp(&b.hand));
+    let hand_time = start.elapsed();
+
+    let mut by_key = evaluations.clone();
+    let start = Instant::now();
+    by_key.sort_unstable_by_key(|evaluation| evaluation.sort_key);
+    let key_time = start.elapsed();
+
+    assert!(
+        by_hand_type
+            .iter()
+            .zip(&by_hand)
+            .zip(&by_key)
+            .all(|(((_, i), a), b)| evaluations[*i].sort_key == b.sort_key
+                && a.sort_key == b.sort_key)
+    );
+
+    println!("Sorted {} hands", count);
+    println!("HandType partial_cmp: {:?}", hand_type_time);
+    println!("Hand ordering: {:?}", hand_time);
+    println!("Packed sort key: {:?}", key_time);
 
-            (HandType::TwoPair(self_hand), HandType::TwoPair(other_hand)) => {
-                compare_eq_hand(self_hand, other_hand)
-            }
-            (HandType::TwoPair(_), _) => Some(Ordering::Greater),
-            (_, HandType::TwoPair(_)) => Some(Ordering::Less),
+    Ok(())
+}
 
-            (HandType::OnePair(self_hand), HandType::OnePair(other_hand)) => {
-                compare_eq_hand(self_hand, other_hand)
-            }
-            (HandType::OnePair(_), _) => Some(Ordering::Greater),
-            (_, HandType::OnePair(_)) => Some(Ordering::Less),
+// the hand-written ordering the rule sets replaced, kept only so the
+// benchmark can compare against it
+mod legacy {
+    use std::cmp::Ordering;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq)]
+    enum Card {
+        A,
+        K,
+        Q,
+        J(bool),
+        T,
+        N(u8),
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub struct Hand {
+        cards: Vec<Card>,
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub enum HandType {
+        FiveOfAKind(Hand),
+        FourOfAKind(Hand),
+        FullHouse(Hand),
+        ThreeOfAKind(Hand),
+        TwoPair(Hand),
+        OnePair(Hand),
+        HighCard(Hand),
+    }
 
-            (HandType::HighCard(self_hand), HandType::HighCard(other_hand)) => {
-                compare_eq_hand(self_hand, other_hand)
+    impl Card {
+        fn from_char(c: char, joker: bool) -> Self {
+            match c {
+                'A' => Card::A,
+                'K' => Card::K,
+                'Q' => Card::Q,
+                'J' => Card::J(joker),
+                'T' => Card::T,
+                _ => Card::N(c.to_digit(10).unwrap() as u8),
             }
         }
     }
-}
 
-fn compare_eq_hand(a: &Hand, b: &Hand) -> Option<Ordering> {
-    for (a, b) in a.cards.iter().zip(&b.cards) {
-        if a != b {
-            return a.partial_cmp(b);
+    impl Hand {
+        fn from_str(hand: &str, joker: bool) -> Self {
+            Hand {
+                cards: hand.chars().map(|x| Card::from_char(x, joker)).collect(),
+            }
         }
     }
 
-    Some(Ordering::Equal)
-}
+    impl PartialOrd for Card {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            match (self, other) {
+                (Card::A, Card::A) => Some(Ordering::Equal),
+                (Card::A, _) => Some(Ordering::Greater),
+                (_, Card::A) => Some(Ordering::Less),
+                (Card::K, Card::K) => Some(Ordering::Equal),
+                (Card::K, _) => Some(Ordering::Greater),
+                (_, Card::K) => Some(Ordering::Less),
+                (Card::Q, Card::Q) => Some(Ordering::Equal),
+                (Card::Q, _) => Some(Ordering::Greater),
+                (_, Card::Q) => Some(Ordering::Less),
+                (Card::J(false), _) => Some(Ordering::Greater),
+                (_, Card::J(false)) => Some(Ordering::Less),
+                (Card::J(true), _) => Some(Ordering::Less),
+                (Card::T, Card::T) => Some(Ordering::Equal),
+                (Card::T, _) => Some(Ordering::Greater),
+                (_, Card::T) => Some(Ordering::Less),
+                (Card::N(a), Card::N(b)) => a.partial_cmp(b),
+                (Card::N(_), _) => Some(Ordering::Greater),
+            }
+        }
+    }
 
-fn part_1(input: &str) -> u32 {
-    let mut hands: Vec<(HandType, usize)> = Vec::new();
+    impl HandType {
+        pub fn from_hand(hand: &str, joker: bool) -> Self {
+            let mut map = HashMap::new();
 
-    for line in input.lines() {
-        let hand = line.split_whitespace().next().unwrap();
-        let bid = line.split_whitespace().nth(1).unwrap();
-        let type_ = HandType::from_hand(hand, false);
+            if joker {
+                let mut jokers = 0;
+                for card in hand.chars() {
+                    if card == 'J' {
+                        jokers += 1;
+                        continue;
+                    }
 
-        hands.push((type_, bid.parse().unwrap()));
-    }
+                    map.entry(card).and_modify(|e| *e += 1).or_insert(1);
+                }
 
-    hands.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-    hands
-        .iter()
-        .enumerate()
-        .fold(0, |acc, (i, (_, bid))| acc + (i + 1) * bid) as u32
-}
+                if jokers == 5 {
+                    return HandType::FiveOfAKind(Hand::from_str(hand, joker));
+                }
 
-fn part_2(input: &str) -> u32 {
-    let mut hands: Vec<(HandType, usize)> = Vec::new();
+                let max = map
+                    .iter()
+                    .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+                    .unwrap()
+                    .0;
+                map.entry(*max)
+                    .and_modify(|e| *e += jokers)
+                    .or_insert(jokers);
+            } else {
+                for card in hand.chars() {
+                    map.entry(card).and_modify(|e| *e += 1).or_insert(1);
+                }
+            }
 
-    for line in input.lines() {
-        let hand = line.split_whitespace().next().unwrap();
-        let bid = line.split_whitespace().nth(1).unwrap();
-        let type_ = HandType::from_hand(hand, true);
+            match map.len() {
+                1 => HandType::FiveOfAKind(Hand::from_str(hand, joker)),
+                2 => {
+                    if map.values().any(|&v| v == 4) {
+                        HandType::FourOfAKind(Hand::from_str(hand, joker))
+                    } else {
+                        HandType::FullHouse(Hand::from_str(hand, joker))
+                    }
+                }
+                3 => {
+                    if map.values().any(|&v| v == 3) {
+                        HandType::ThreeOfAKind(Hand::from_str(hand, joker))
+                    } else {
+                        HandType::TwoPair(Hand::from_str(hand, joker))
+                    }
+                }
+                4 => HandType::OnePair(Hand::from_str(hand, joker)),
+                _ => HandType::HighCard(Hand::from_str(hand, joker)),
+            }
+        }
+    }
 
-        hands.push((type_, bid.parse().unwrap()));
+    impl PartialOrd for HandType {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            match (self, other) {
+                (HandType::FiveOfAKind(self_hand), HandType::FiveOfAKind(other_hand)) => {
+                    compare_eq_hand(self_hand, other_hand)
+                }
+                (HandType::FiveOfAKind(_), _) => Some(Ordering::Greater),
+                (_, HandType::FiveOfAKind(_)) => Some(Ordering::Less),
+                (HandType::FourOfAKind(self_hand), HandType::FourOfAKind(other_hand)) => {
+                    compare_eq_hand(self_hand, other_hand)
+                }
+                (HandType::FourOfAKind(_), _) => Some(Ordering::Greater),
+                (_, HandType::FourOfAKind(_)) => Some(Ordering::Less),
+                (HandType::FullHouse(self_hand), HandType::FullHouse(other_hand)) => {
+                    compare_eq_hand(self_hand, other_hand)
+                }
+                (HandType::FullHouse(_), _) => Some(Ordering::Greater),
+                (_, HandType::FullHouse(_)) => Some(Ordering::Less),
+                (HandType::ThreeOfAKind(self_hand), HandType::ThreeOfAKind(other_hand)) => {
+                    compare_eq_hand(self_hand, other_hand)
+                }
+                (HandType::ThreeOfAKind(_), _) => Some(Ordering::Greater),
+                (_, HandType::ThreeOfAKind(_)) => Some(Ordering::Less),
+                (HandType::TwoPair(self_hand), HandType::TwoPair(other_hand)) => {
+                    compare_eq_hand(self_hand, other_hand)
+                }
+                (HandType::TwoPair(_), _) => Some(Ordering::Greater),
+                (_, HandType::TwoPair(_)) => Some(Ordering::Less),
+                (HandType::OnePair(self_hand), HandType::OnePair(other_hand)) => {
+                    compare_eq_hand(self_hand, other_hand)
+                }
+                (HandType::OnePair(_), _) => Some(Ordering::Greater),
+                (_, HandType::OnePair(_)) => Some(Ordering::Less),
+                (HandType::HighCard(self_hand), HandType::HighCard(other_hand)) => {
+                    compare_eq_hand(self_hand, other_hand)
+                }
+            }
+        }
     }
 
-    hands.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-    hands
-        .iter()
-        .enumerate()
-        .fold(0, |acc, (i, (_, bid))| acc + (i + 1) * bid) as u32
+    fn compare_eq_hand(a: &Hand, b: &Hand) -> Option<Ordering> {
+        for (a, b) in a.cards.iter().zip(&b.cards) {
+            if a != b {
+                return a.partial_cmp(b);
+            }
+        }
+
+        Some(Ordering::Equal)
+    }
 }
 
 #[cfg(test)]
@@ -247,7 +491,7 @@ mod tests {
     fn test_part_1() -> Result<()> {
         let input = fs::read_to_string("input/test/day7.txt")?;
 
-        let result = part_1(&input);
+        let result = part_1(&input)?;
         assert_eq!(result, 6440);
 
         Ok(())
@@ -257,9 +501,99 @@ mod tests {
     fn test_part_2() -> Result<()> {
         let input = fs::read_to_string("input/test/day7.txt")?;
 
-        let result = part_2(&input);
+        let result = part_2(&input)?;
         assert_eq!(result, 5905);
 
         Ok(())
     }
+
+    #[test]
+    fn test_rules() -> Result<()> {
+        let standard = Rules::standard();
+        let jokers = Rules::jokers();
+
+        assert!(standard.evaluate("33332")?.hand > standard.evaluate("2AAAA")?.hand);
+        assert!(standard.evaluate("77888")?.hand > standard.evaluate("77788")?.hand);
+        assert!(standard.evaluate("JJ234")?.hand > standard.evaluate("TT234")?.hand);
+        assert!(jokers.evaluate("J2345")?.hand < jokers.evaluate("22345")?.hand);
+        assert_eq!(standard.evaluate("JKKK2")?.hand.category, 3);
+        assert!(jokers.evaluate("JKKK2")?.hand < jokers.evaluate("QQQQ2")?.hand);
+        assert_eq!(jokers.evaluate("JJJJJ")?.hand.category, 6);
+        assert_eq!(jokers.evaluate("QJJQ2")?.hand.category, 5);
+
+        let wild_twos = Rules {
+            card_order: "23456789TJQKA".chars().collect(),
+            wild_cards: vec!['2'],
+            ..Rules::standard()
+        };
+        assert_eq!(wild_twos.evaluate("2KK2A")?.hand.category, 5);
+
+        let three_card = Rules {
+            hand_size: 3,
+            categories: vec![
+                ("high card", vec![1, 1, 1]),
+                ("pair", vec![2, 1]),
+                ("three of a kind", vec![3]),
+            ],
+            ..Rules::standard()
+        };
+        assert!(three_card.evaluate("AKQ")?.hand < three_card.evaluate("223")?.hand);
+        assert!(three_card.evaluate("2233").is_err());
+        assert!(standard.evaluate("2345X").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate() -> Result<()> {
+        let evaluation = Rules::jokers().evaluate("KTJJT")?;
+
+        assert_eq!(evaluation.hand.category, 5);
+        assert_eq!(evaluation.counts, vec![('T', 2), ('J', 2), ('K', 1)]);
+        assert_eq!(evaluation.wild_as, Some('T'));
+        assert_eq!(evaluation.hand.ranks, vec![11, 9, 0, 0, 9]);
+
+        let evaluation = Rules::standard().evaluate("KTJJT")?;
+
+        assert_eq!(evaluation.hand.category, 2);
+        assert_eq!(evaluation.counts, vec![('J', 2), ('T', 2), ('K', 1)]);
+        assert_eq!(evaluation.wild_as, None);
+
+        assert_eq!(Rules::jokers().evaluate("JJJJJ")?.wild_as, Some('A'));
+
+        let input = fs::read_to_string("input/test/day7.txt")?;
+        let ranked: Vec<String> = ranked_hands(&input, &Rules::jokers())?
+            .into_iter()
+            .map(|(evaluation, _)| evaluation.cards)
+            .collect();
+        assert_eq!(ranked, vec!["32T3K", "KK677", "T55J5", "QQQJA", "KTJJT"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_key() -> Result<()> {
+        let rules = Rules::standard();
+
+        assert_eq!(rules.evaluate("22222")?.sort_key, Some(6 << 20));
+        assert_eq!(rules.evaluate("AKQJT")?.sort_key, Some(0xcba98));
+
+        // 16 ranks of 4 bits leave no room for the category, so these hands
+        // are ranked through `Hand`'s `Ord` instead
+        let wide = Rules {
+            hand_size: 16,
+            categories: vec![("two groups", vec![8, 8]), ("one group", vec![16])],
+            ..Rules::standard()
+        };
+        assert_eq!(wide.sort_key(0, &[0; 16]), None);
+        let input = "3333333322222222 1\n2222222233333333 10\nAAAAAAAAAAAAAAAA 100";
+        assert_eq!(total_winnings(input, &wide)?, 312);
+
+        let input = "AAAAA 18446744073709551615\n22222 1";
+        assert!(part_1(input).is_err());
+        assert_eq!(winnings(0, u64::MAX)?, u64::MAX);
+        assert!(winnings(1, u64::MAX).is_err());
+
+        Ok(())
+    }
 }
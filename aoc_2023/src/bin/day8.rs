@@ -1,13 +1,64 @@
 use std::collections::HashMap;
+use std::env;
 use std::fs;
+use std::time::Instant;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 
 fn main() -> Result<()> {
     let input = fs::read_to_string("input/day8.txt")?;
+    let args: Vec<String> = env::args().collect();
 
-    let part1 = part_1(&input);
-    let part2 = part_2(&input);
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let node_count = args
+            .get(2)
+            .map(|x| x.parse())
+            .transpose()?
+            .unwrap_or(1_000_000);
+        let steps = args
+            .get(3)
+            .map(|x| x.parse())
+            .transpose()?
+            .unwrap_or(10_000_000);
+        benchmark(node_count, steps)?;
+
+        return Ok(());
+    }
+
+    if args.get(1).map(String::as_str) == Some("dot") {
+        let (instructions, nodes) = input.split_once("\n\n").unwrap();
+        let map = NodeMap::new(nodes, &Rules::ghosts())?;
+        let instructions = map.compile(instructions)?;
+
+        let walk = match args.get(2) {
+            Some(start) => Some((map.id(start)?, instructions.as_slice())),
+            None => None,
+        };
+        print!("{}", map.to_dot(walk));
+
+        return Ok(());
+    }
+
+    if args.get(1).map(String::as_str) == Some("where") {
+        let (instructions, nodes) = input.split_once("\n\n").unwrap();
+        let map = NodeMap::new(nodes, &Rules::ghosts())?;
+        let instructions = map.compile(instructions)?;
+
+        let start = map.id(args.get(2).map(String::as_str).unwrap_or("AAA"))?;
+        let steps = args.get(3).map(|x| x.parse()).transpose()?.unwrap_or(0);
+        let (node, end_visits) = JumpTable::new(&map, &instructions, steps).walk(start, steps)?;
+
+        println!(
+            "Position after {} steps: {}",
+            steps, map.names[node as usize]
+        );
+        println!("End node visits: {}", end_visits);
+
+        return Ok(());
+    }
+
+    let part1 = part_1(&input)?;
+    let part2 = part_2(&input)?;
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
@@ -15,89 +66,552 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+struct Rules {
+    alphabet: Vec<char>,
+    is_start: fn(&str) -> bool,
+    is_end: fn(&str) -> bool,
+}
+
+impl Rules {
+    fn camel() -> Self {
+        Rules {
+            alphabet: vec!['L', 'R'],
+            is_start: |name| name == "AAA",
+            is_end: |name| name == "ZZZ",
+        }
+    }
+
+    fn ghosts() -> Self {
+        Rules {
+            alphabet: vec!['L', 'R'],
+            is_start: |name| name.ends_with('A'),
+            is_end: |name| name.ends_with('Z'),
+        }
+    }
+}
+
 struct NodeMap<'a> {
-    nodes: HashMap<&'a str, (&'a str, &'a str)>,
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, u32>,
+    offsets: Vec<usize>,
+    children: Vec<u32>,
+    alphabet: Vec<char>,
+    starts: Vec<bool>,
+    ends: Vec<bool>,
 }
 
 impl<'a> NodeMap<'a> {
-    fn new(str_nodes: &'a str) -> Self {
-        let mut nodes = HashMap::new();
+    fn new(str_nodes: &'a str, rules: &Rules) -> Result<Self> {
+        let mut names = Vec::new();
+        let mut edges = Vec::new();
 
         for line in str_nodes.lines() {
-            let current_node = line.split(" = ").nth(0).unwrap();
-            let (left_node, right_node) = line
-                .split(" = ")
-                .nth(1)
-                .unwrap()
+            let (current_node, children) = line
+                .split_once(" = ")
+                .ok_or_else(|| anyhow!("invalid node line {:?}", line))?;
+            let children: Vec<&str> = children
                 .trim_start_matches('(')
                 .trim_end_matches(')')
-                .split_once(", ")
-                .unwrap();
+                .split(", ")
+                .collect();
 
-            nodes.insert(current_node, (left_node, right_node));
+            names.push(current_node);
+            edges.push(children);
         }
 
-        Self { nodes }
+        let ids: HashMap<&str, u32> = names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| (*name, id as u32))
+            .collect();
+
+        // children of node n live at offsets[n]..offsets[n + 1]
+        let mut offsets = vec![0];
+        let mut children = Vec::new();
+        for node_children in edges {
+            for child in node_children {
+                let id = ids
+                    .get(child)
+                    .ok_or_else(|| anyhow!("node {} is not in the map", child))?;
+                children.push(*id);
+            }
+            offsets.push(children.len());
+        }
+
+        let starts = names.iter().map(|name| (rules.is_start)(name)).collect();
+        let ends = names.iter().map(|name| (rules.is_end)(name)).collect();
+
+        Ok(Self {
+            names,
+            ids,
+            offsets,
+            children,
+            alphabet: rules.alphabet.clone(),
+            starts,
+            ends,
+        })
     }
 
-    fn next_node(&self, current_node: &str, instruction: char) -> &str {
-        let (left_node, right_node) = self.nodes.get(&current_node).unwrap();
-        match instruction {
-            'L' => left_node,
-            'R' => right_node,
-            _ => panic!("Invalid instruction"),
+    fn compile(&self, instructions: &str) -> Result<Vec<u8>> {
+        let directions = instructions
+            .chars()
+            .map(|instruction| {
+                self.alphabet
+                    .iter()
+                    .position(|c| *c == instruction)
+                    .map(|direction| direction as u8)
+                    .ok_or_else(|| anyhow!("invalid instruction {:?}", instruction))
+            })
+            .collect::<Result<Vec<u8>>>()?;
+
+        // every node must have a child for every instruction used
+        let highest = *directions
+            .iter()
+            .max()
+            .ok_or_else(|| anyhow!("no instructions"))? as usize;
+        if let Some(node) = (0..self.names.len() as u32).find(|&node| self.arity(node) <= highest) {
+            bail!(
+                "instruction {:?} needs {} children but node {} has {}",
+                self.alphabet[highest],
+                highest + 1,
+                self.names[node as usize],
+                self.arity(node)
+            );
+        }
+
+        Ok(directions)
+    }
+
+    fn arity(&self, node: u32) -> usize {
+        self.offsets[node as usize + 1] - self.offsets[node as usize]
+    }
+
+    fn id(&self, name: &str) -> Result<u32> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("node {} is not in the map", name))
+    }
+
+    fn start_nodes(&self) -> Vec<u32> {
+        (0..self.names.len() as u32)
+            .filter(|&node| self.starts[node as usize])
+            .collect()
+    }
+
+    fn is_end(&self, node: u32) -> bool {
+        self.ends[node as usize]
+    }
+
+    fn next_node(&self, current_node: u32, direction: u8) -> u32 {
+        self.children[self.offsets[current_node as usize] + direction as usize]
+    }
+
+    fn ghost_cycle(&self, start_node: u32, instructions: &[u8]) -> GhostCycle {
+        // the walk is fully determined by (node, instruction index), so
+        // floyd's cycle detection finds the lead-in and cycle length without
+        // remembering visited states
+        let step = |(node, index): (u32, usize)| {
+            (
+                self.next_node(node, instructions[index]),
+                (index + 1) % instructions.len(),
+            )
+        };
+        let start = (start_node, 0);
+
+        let mut tortoise = step(start);
+        let mut hare = step(step(start));
+        while tortoise != hare {
+            tortoise = step(tortoise);
+            hare = step(step(hare));
+        }
+
+        let mut lead_in = 0;
+        tortoise = start;
+        while tortoise != hare {
+            tortoise = step(tortoise);
+            hare = step(hare);
+            lead_in += 1;
+        }
+
+        let mut length = 1;
+        hare = step(tortoise);
+        while tortoise != hare {
+            hare = step(hare);
+            length += 1;
+        }
+
+        let mut end_hits = Vec::new();
+        let mut state = start;
+        for steps in 0..lead_in + length {
+            if self.is_end(state.0) {
+                end_hits.push(steps);
+            }
+            state = step(state);
+        }
+
+        GhostCycle {
+            lead_in,
+            length,
+            end_hits,
+        }
+    }
+
+    fn to_dot(&self, walk: Option<(u32, &[u8])>) -> String {
+        // 1 marks an edge on the lead-in of the walk, 2 an edge on its cycle
+        let mut highlighted = vec![0; self.children.len()];
+        if let Some((start_node, instructions)) = walk {
+            let cycle = self.ghost_cycle(start_node, instructions);
+            let mut node = start_node;
+            for steps in 0..cycle.lead_in + cycle.length {
+                let direction = instructions[steps % instructions.len()];
+                let edge = self.offsets[node as usize] + direction as usize;
+                let kind = if steps < cycle.lead_in { 1 } else { 2 };
+                highlighted[edge] = highlighted[edge].max(kind);
+                node = self.next_node(node, direction);
+            }
+        }
+
+        let mut dot = String::from("digraph network {\n    node [shape=circle];\n");
+
+        for (node, name) in self.names.iter().enumerate() {
+            let style = if self.starts[node] {
+                " [style=filled, fillcolor=palegreen]"
+            } else if self.ends[node] {
+                " [style=filled, fillcolor=lightcoral]"
+            } else {
+                ""
+            };
+            dot.push_str(&format!("    \"{}\"{};\n", name, style));
+        }
+
+        for (node, name) in self.names.iter().enumerate() {
+            let edges = self.offsets[node]..self.offsets[node + 1];
+            for (direction, (child, kind)) in self.children[edges.clone()]
+                .iter()
+                .zip(&highlighted[edges])
+                .enumerate()
+            {
+                let label = match self.alphabet.get(direction) {
+                    Some(c) => c.to_string(),
+                    None => direction.to_string(),
+                };
+                let style = match kind {
+                    1 => ", color=blue, penwidth=2",
+                    2 => ", color=orange, penwidth=3",
+                    _ => "",
+                };
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label=\"{}\"{}];\n",
+                    name, self.names[*child as usize], label, style
+                ));
+            }
         }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+struct JumpTable<'m, 'a> {
+    map: &'m NodeMap<'a>,
+    instructions: &'m [u8],
+    jumps: Vec<Vec<u32>>,
+    end_visits: Vec<Vec<u64>>,
+}
+
+impl<'m, 'a> JumpTable<'m, 'a> {
+    fn new(map: &'m NodeMap<'a>, instructions: &'m [u8], max_steps: u64) -> Self {
+        let node_count = map.names.len() as u32;
+        let mut jumps = vec![Vec::with_capacity(node_count as usize)];
+        let mut end_visits = vec![Vec::with_capacity(node_count as usize)];
+
+        // level 0 is one full pass over the instructions
+        for start in 0..node_count {
+            let mut node = start;
+            let mut visits = 0;
+            for direction in instructions {
+                node = map.next_node(node, *direction);
+                visits += map.is_end(node) as u64;
+            }
+            jumps[0].push(node);
+            end_visits[0].push(visits);
+        }
+
+        // level k + 1 is two level k jumps back to back
+        let cycles = max_steps / instructions.len() as u64;
+        let levels = (u64::BITS - cycles.leading_zeros()).max(1) as usize;
+        for level in 1..levels {
+            let (jump, visits) = (0..node_count as usize)
+                .map(|node| {
+                    let middle = jumps[level - 1][node] as usize;
+                    (
+                        jumps[level - 1][middle],
+                        end_visits[level - 1][node] + end_visits[level - 1][middle],
+                    )
+                })
+                .unzip();
+            jumps.push(jump);
+            end_visits.push(visits);
+        }
+
+        Self {
+            map,
+            instructions,
+            jumps,
+            end_visits,
+        }
+    }
+
+    fn walk(&self, start_node: u32, steps: u64) -> Result<(u32, u64)> {
+        let len = self.instructions.len() as u64;
+        let cycles = steps / len;
+        if cycles.checked_shr(self.jumps.len() as u32).unwrap_or(0) != 0 {
+            bail!("jump table was not built for {} steps", steps);
+        }
+
+        let mut node = start_node;
+        let mut visits = 0;
+        for level in 0..self.jumps.len() {
+            if cycles & (1 << level) != 0 {
+                visits += self.end_visits[level][node as usize];
+                node = self.jumps[level][node as usize];
+            }
+        }
+
+        for direction in &self.instructions[..(steps % len) as usize] {
+            node = self.map.next_node(node, *direction);
+            visits += self.map.is_end(node) as u64;
+        }
+
+        Ok((node, visits))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct GhostCycle {
+    lead_in: usize,
+    length: usize,
+    end_hits: Vec<usize>,
+}
+
+impl GhostCycle {
+    fn is_end_at(&self, steps: usize) -> bool {
+        if steps < self.lead_in {
+            return self.end_hits.contains(&steps);
+        }
+
+        let in_cycle = self.lead_in + (steps - self.lead_in) % self.length;
+        self.end_hits.contains(&in_cycle)
+    }
+
+    fn cycle_offsets(&self) -> Vec<usize> {
+        self.end_hits
+            .iter()
+            .filter(|&&hit| hit >= self.lead_in)
+            .copied()
+            .collect()
     }
 }
 
-fn part_1(input: &str) -> usize {
+fn part_1(input: &str) -> Result<usize> {
     let (instructions, nodes) = input.split_once("\n\n").unwrap();
-    let map = NodeMap::new(nodes);
+    let map = NodeMap::new(nodes, &Rules::camel())?;
+    let instructions = map.compile(instructions)?;
+
+    let start_node = *map
+        .start_nodes()
+        .first()
+        .ok_or_else(|| anyhow!("no start node in the map"))?;
 
-    steps_to_end("AAA", &map, instructions)
+    steps_to_end(start_node, &map, &instructions)
 }
 
-fn part_2(input: &str) -> usize {
+fn part_2(input: &str) -> Result<usize> {
     let (instructions, nodes) = input.split_once("\n\n").unwrap();
-    let map = NodeMap::new(nodes);
+    let map = NodeMap::new(nodes, &Rules::ghosts())?;
+    let instructions = map.compile(instructions)?;
+
+    let cycles: Vec<GhostCycle> = map
+        .start_nodes()
+        .into_iter()
+        .map(|node| map.ghost_cycle(node, &instructions))
+        .collect();
 
-    let start_nodes = nodes
-        .lines()
-        .map(|line| line.split(" = ").next().unwrap())
-        .filter(|line| line.ends_with('A'))
-        .collect::<Vec<&str>>();
+    all_at_end(&cycles)?.ok_or_else(|| anyhow!("ghosts never reach end nodes at the same time"))
+}
 
-    start_nodes
+fn all_at_end(cycles: &[GhostCycle]) -> Result<Option<usize>> {
+    // before every ghost is inside its cycle only the lead-in hits can line up
+    let Some(lead_in) = cycles.iter().map(|cycle| cycle.lead_in).max() else {
+        return Ok(None);
+    };
+    let early = cycles
         .iter()
-        .map(|node| steps_to_end(node, &map, instructions))
-        .reduce(least_common_multiple)
-        .unwrap()
+        .flat_map(|cycle| cycle.end_hits.iter())
+        .filter(|&&steps| steps < lead_in)
+        .filter(|&&steps| cycles.iter().all(|cycle| cycle.is_end_at(steps)))
+        .min()
+        .copied();
+
+    if early.is_some() {
+        return Ok(early);
+    }
+
+    let mut congruences: Vec<(i128, i128)> = vec![(0, 1)];
+    for cycle in cycles {
+        let mut combined = Vec::new();
+        for &congruence in &congruences {
+            for offset in cycle.cycle_offsets() {
+                let next = (offset as i128, cycle.length as i128);
+                if let Some(solution) = chinese_remainder(congruence, next)? {
+                    combined.push(solution);
+                }
+            }
+        }
+        congruences = combined;
+    }
+
+    let overflow = || anyhow!("ghosts line up after more steps than fit in an i128");
+    let lead_in = lead_in as i128;
+    let mut earliest: Option<i128> = None;
+    for (remainder, modulus) in congruences {
+        let steps = if remainder >= lead_in {
+            remainder
+        } else {
+            ((lead_in - remainder - 1) / modulus + 1)
+                .checked_mul(modulus)
+                .and_then(|skipped| skipped.checked_add(remainder))
+                .ok_or_else(overflow)?
+        };
+        earliest = Some(earliest.map_or(steps, |earliest| earliest.min(steps)));
+    }
+
+    earliest
+        .map(|steps| {
+            usize::try_from(steps)
+                .map_err(|_| anyhow!("ghosts line up after {} steps, more than a usize", steps))
+        })
+        .transpose()
+}
+
+fn chinese_remainder(
+    (a1, m1): (i128, i128),
+    (a2, m2): (i128, i128),
+) -> Result<Option<(i128, i128)>> {
+    let overflow = || anyhow!("combined ghost cycle length overflows an i128");
+    let (gcd, x, _) = extended_gcd(m1, m2);
+    if (a2 - a1) % gcd != 0 {
+        return Ok(None);
+    }
+
+    let modulus = (m1 / gcd).checked_mul(m2).ok_or_else(overflow)?;
+    let k = ((a2 - a1) / gcd)
+        .checked_mul(x)
+        .ok_or_else(overflow)?
+        .rem_euclid(m2 / gcd);
+    let remainder = m1
+        .checked_mul(k)
+        .and_then(|shift| shift.checked_add(a1))
+        .ok_or_else(overflow)?
+        .rem_euclid(modulus);
+
+    Ok(Some((remainder, modulus)))
+}
+
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
+    }
+
+    let (gcd, x, y) = extended_gcd(b, a % b);
+    (gcd, y, x - (a / b) * y)
 }
 
-fn steps_to_end(start_node: &str, map: &NodeMap, instructions: &str) -> usize {
+fn steps_to_end(start_node: u32, map: &NodeMap, instructions: &[u8]) -> Result<usize> {
+    // after visiting more states than there are (node, index) pairs the walk
+    // must be going round a cycle without an end node
+    let states = map.names.len() * instructions.len();
     let mut steps = 0;
     let mut current_node = start_node;
 
-    while !current_node.ends_with('Z') {
-        for instruction in instructions.chars() {
-            current_node = map.next_node(current_node, instruction);
-            steps += 1;
+    while !map.is_end(current_node) {
+        if steps == states {
+            bail!(
+                "no end node is reachable from {}",
+                map.names[start_node as usize]
+            );
         }
+
+        current_node = map.next_node(current_node, instructions[steps % instructions.len()]);
+        steps += 1;
     }
 
-    steps
+    Ok(steps)
 }
 
-fn least_common_multiple(a: usize, b: usize) -> usize {
-    (a * b) / greater_common_divisor(a, b)
-}
+fn benchmark(node_count: usize, steps: usize) -> Result<()> {
+    let mut seed: u64 = 0x2545f4914f6cdd1d;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
 
-fn greater_common_divisor(a: usize, b: usize) -> usize {
-    if b == 0 {
-        return a;
+    let names: Vec<String> = (0..node_count).map(|i| format!("N{:07}", i)).collect();
+    let instructions: String = (0..293)
+        .map(|_| if next() % 2 == 0 { 'L' } else { 'R' })
+        .collect();
+    let nodes: String = names
+        .iter()
+        .map(|name| {
+            let left = &names[next() as usize % node_count];
+            let right = &names[next() as usize % node_count];
+            format!("{} = ({}, {})\n", name, left, right)
+        })
+        .collect();
+
+    // the string-keyed walk this replaced
+    let mut map: HashMap<&str, (&str, &str)> = HashMap::new();
+    for line in nodes.lines() {
+        let (current_node, children) = line.split_once(" = ").unwrap();
+        let (left_node, right_node) = children
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .split_once(", ")
+            .unwrap();
+        map.insert(current_node, (left_node, right_node));
+    }
+
+    let start = Instant::now();
+    let mut current_node = names[0].as_str();
+    for instruction in instructions.chars().cycle().take(steps) {
+        let (left_node, right_node) = map[current_node];
+        current_node = match instruction {
+            'L' => left_node,
+            _ => right_node,
+        };
+    }
+    let string_time = start.elapsed();
+
+    let node_map = NodeMap::new(&nodes, &Rules::ghosts())?;
+    let directions = node_map.compile(&instructions)?;
+
+    let start = Instant::now();
+    let mut node = 0;
+    for direction in directions.iter().cycle().take(steps) {
+        node = node_map.next_node(node, *direction);
     }
+    let interned_time = start.elapsed();
 
-    greater_common_divisor(b, a % b)
+    assert_eq!(node_map.names[node as usize], current_node);
+
+    println!("Walked {} steps over {} nodes", steps, node_count);
+    println!("String keys: {:?}", string_time);
+    println!("Interned: {:?}", interned_time);
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -109,8 +623,8 @@ mod tests {
         let input_1 = fs::read_to_string("input/test/day8_part_1_1.txt")?;
         let input_2 = fs::read_to_string("input/test/day8_part_1_2.txt")?;
 
-        let result_1 = part_1(&input_1);
-        let result_2 = part_1(&input_2);
+        let result_1 = part_1(&input_1)?;
+        let result_2 = part_1(&input_2)?;
 
         assert_eq!(result_1, 2);
         assert_eq!(result_2, 6);
@@ -122,9 +636,193 @@ mod tests {
     fn test_part_2() -> Result<()> {
         let input = fs::read_to_string("input/test/day8_part_2.txt")?;
 
-        let result = part_2(&input);
+        let result = part_2(&input)?;
         assert_eq!(result, 6);
 
         Ok(())
     }
+
+    #[test]
+    fn test_ghost_cycle() -> Result<()> {
+        let input = fs::read_to_string("input/test/day8_part_2.txt")?;
+        let (instructions, nodes) = input.split_once("\n\n").unwrap();
+        let map = NodeMap::new(nodes, &Rules::ghosts())?;
+        let instructions = map.compile(instructions)?;
+
+        let cycle = map.ghost_cycle(map.id("11A")?, &instructions);
+        assert_eq!(
+            cycle,
+            GhostCycle {
+                lead_in: 1,
+                length: 2,
+                end_hits: vec![2]
+            }
+        );
+
+        let cycle = map.ghost_cycle(map.id("22A")?, &instructions);
+        assert_eq!((cycle.lead_in, cycle.length), (1, 6));
+        assert_eq!(cycle.end_hits, vec![3, 6]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_at_end() -> Result<()> {
+        let cycle = |lead_in, length, end_hits| GhostCycle {
+            lead_in,
+            length,
+            end_hits,
+        };
+
+        assert_eq!(chinese_remainder((2, 3), (3, 5))?, Some((8, 15)));
+        assert_eq!(chinese_remainder((1, 4), (3, 6))?, Some((9, 12)));
+        assert_eq!(chinese_remainder((0, 4), (1, 6))?, None);
+
+        assert_eq!(
+            all_at_end(&[cycle(0, 4, vec![1]), cycle(0, 6, vec![3])])?,
+            Some(9)
+        );
+        assert_eq!(
+            all_at_end(&[cycle(5, 4, vec![2, 6]), cycle(0, 6, vec![2])])?,
+            Some(2)
+        );
+        assert_eq!(
+            all_at_end(&[cycle(0, 4, vec![0]), cycle(0, 6, vec![1])])?,
+            None
+        );
+
+        // a combined period above 2^64 is solved exactly, then rejected
+        // rather than truncated to a usize
+        let primes = [10007, 10009, 10037, 10039, 10061];
+        let combined = primes.iter().try_fold((0, 1), |congruence, &p| {
+            chinese_remainder(congruence, (p - 1, p))?.ok_or_else(|| anyhow!("no solution"))
+        })?;
+        assert_eq!(combined, (101538353409718995448, 101538353409718995449));
+        let cycles: Vec<GhostCycle> = primes
+            .iter()
+            .map(|&p| cycle(0, p as usize, vec![p as usize - 1]))
+            .collect();
+        assert!(all_at_end(&cycles).is_err());
+
+        let primes = [1000000007, 1000000009, 1000000021, 1000000033, 998244353];
+        let cycles: Vec<GhostCycle> = primes.iter().map(|&p| cycle(0, p, vec![1])).collect();
+        assert!(all_at_end(&cycles).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_steps_to_end() -> Result<()> {
+        let map = NodeMap::new(
+            "AAA = (BBB, CCC)\nBBB = (ZZZ, AAA)\nCCC = (CCC, CCC)\nZZZ = (ZZZ, ZZZ)",
+            &Rules::camel(),
+        )?;
+        let steps = |start: &str, instructions: &str| -> Result<usize> {
+            steps_to_end(map.id(start)?, &map, &map.compile(instructions)?)
+        };
+
+        assert_eq!(steps("AAA", "LLR")?, 2);
+        assert_eq!(steps("ZZZ", "LLR")?, 0);
+        assert!(steps("AAA", "R").is_err());
+        assert!(steps("AAA", "LRR").is_err());
+        assert!(steps("XXX", "L").is_err());
+        assert!(steps("AAA", "LX").is_err());
+
+        assert!(NodeMap::new("AAA = (BBB, BBB)", &Rules::camel()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jump_table() -> Result<()> {
+        let input = fs::read_to_string("input/test/day8_part_2.txt")?;
+        let (instructions, nodes) = input.split_once("\n\n").unwrap();
+        let map = NodeMap::new(nodes, &Rules::ghosts())?;
+        let instructions = map.compile(instructions)?;
+        let table = JumpTable::new(&map, &instructions, 1000);
+
+        for start in map.start_nodes() {
+            let mut node = start;
+            let mut visits = 0;
+            for steps in 0..1000u64 {
+                assert_eq!(table.walk(start, steps)?, (node, visits));
+
+                node = map.next_node(node, instructions[steps as usize % instructions.len()]);
+                visits += map.is_end(node) as u64;
+            }
+        }
+
+        // 22A reaches an end node on every third step from step 3 onwards
+        let table = JumpTable::new(&map, &instructions, 1_000_000_000_000);
+        let (node, visits) = table.walk(map.id("22A")?, 1_000_000_000_000)?;
+        assert_eq!(map.names[node as usize], "22B");
+        assert_eq!(visits, 333_333_333_333);
+        assert!(table.walk(map.id("22A")?, u64::MAX).is_err());
+
+        // a one-instruction program needs all 64 levels for u64::MAX steps
+        let instructions = map.compile("L")?;
+        let table = JumpTable::new(&map, &instructions, u64::MAX);
+        let (node, visits) = table.walk(map.id("22A")?, u64::MAX)?;
+        assert_eq!(map.names[node as usize], "22Z");
+        assert_eq!(visits, u64::MAX / 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_dot() -> Result<()> {
+        let input = fs::read_to_string("input/test/day8_part_2.txt")?;
+        let (instructions, nodes) = input.split_once("\n\n").unwrap();
+        let map = NodeMap::new(nodes, &Rules::ghosts())?;
+        let instructions = map.compile(instructions)?;
+
+        let dot = map.to_dot(None);
+        assert!(dot.starts_with("digraph network {"));
+        assert!(dot.contains("\"11A\" [style=filled, fillcolor=palegreen];"));
+        assert!(dot.contains("\"11Z\" [style=filled, fillcolor=lightcoral];"));
+        assert!(dot.contains("\"11A\" -> \"11B\" [label=\"L\"];"));
+        assert_eq!(dot.matches(" -> ").count(), 16);
+
+        let dot = map.to_dot(Some((map.id("11A")?, &instructions)));
+        assert!(dot.contains("\"11A\" -> \"11B\" [label=\"L\", color=blue, penwidth=2];"));
+        assert!(dot.contains("\"11B\" -> \"11Z\" [label=\"R\", color=orange, penwidth=3];"));
+        assert!(dot.contains("\"11Z\" -> \"11B\" [label=\"L\", color=orange, penwidth=3];"));
+        assert!(dot.contains("\"11A\" -> \"XXX\" [label=\"R\"];"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_n_ary_nodes() -> Result<()> {
+        let rules = Rules {
+            alphabet: vec!['L', 'C', 'R'],
+            is_start: |name| name.starts_with("start"),
+            is_end: |name| name.starts_with("end"),
+        };
+        let nodes = "start = (a, b, c)\na = (a, end1, start)\nb = (b, b, b)\nc = (a, c, c)\nend1 = (end1, end1, end1)";
+        let map = NodeMap::new(nodes, &rules)?;
+        let start = map.id("start")?;
+
+        assert_eq!(map.start_nodes(), vec![start]);
+        assert_eq!(steps_to_end(start, &map, &map.compile("RLC")?)?, 3);
+        assert_eq!(steps_to_end(start, &map, &map.compile("LC")?)?, 2);
+        assert!(steps_to_end(start, &map, &map.compile("C")?).is_err());
+        assert!(map.compile("LX").is_err());
+        assert!(map
+            .to_dot(None)
+            .contains("\"start\" -> \"b\" [label=\"C\"];"));
+
+        let digits = Rules {
+            alphabet: "0123456789".chars().collect(),
+            ..Rules::ghosts()
+        };
+        let map = NodeMap::new(
+            "AAA = (BBB, CCC, ZZZ)\nBBB = (ZZZ, ZZZ)\nCCC = (CCC)\nZZZ = (ZZZ, AAA, BBB)",
+            &digits,
+        )?;
+        assert_eq!(steps_to_end(map.id("AAA")?, &map, &map.compile("0")?)?, 2);
+        assert!(map.compile("1").is_err());
+
+        Ok(())
+    }
 }
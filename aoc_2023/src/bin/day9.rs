@@ -1,12 +1,49 @@
-use std::fs;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 
 fn main() -> Result<()> {
     let input = fs::read_to_string("input/day9.txt")?;
+    let args: Vec<String> = env::args().collect();
 
-    let part1 = part_1(&input);
-    let part2 = part_2(&input);
+    if args.get(1).map(String::as_str) == Some("fit") {
+        for line in input.lines() {
+            let polynomial = Polynomial::fit(&parse_history(line)?)?;
+            match polynomial.expanded() {
+                Ok(expanded) => println!("degree {}: {}", polynomial.degree(), expanded),
+                Err(error) => println!("degree {}: {}", polynomial.degree(), error),
+            }
+        }
+
+        return Ok(());
+    }
+
+    if args.get(1).map(String::as_str) == Some("predict") {
+        let index = args
+            .get(2)
+            .ok_or_else(|| anyhow!("usage: day9 predict <index>"))?
+            .parse()?;
+        println!("Sum at index {}: {}", index, sum_at(&input, |_| index)?);
+
+        return Ok(());
+    }
+
+    if args.get(1).map(String::as_str) == Some("stream") {
+        let (next, previous) = match args.get(2) {
+            Some(path) => sum_extrapolations(BufReader::new(File::open(path)?))?,
+            None => sum_extrapolations(io::stdin().lock())?,
+        };
+        println!("Next values: {}", next);
+        println!("Previous values: {}", previous);
+
+        return Ok(());
+    }
+
+    let part1 = part_1(&input)?;
+    let part2 = part_2(&input)?;
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
@@ -14,54 +51,409 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn part_1(input: &str) -> i64 {
-    let mut sum = 0;
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    fn new(numerator: i128, denominator: i128) -> Option<Self> {
+        let divisor = i128::try_from(gcd(numerator.unsigned_abs(), denominator.unsigned_abs()))
+            .ok()?
+            .checked_mul(denominator.signum())?;
+        Some(Self {
+            numerator: numerator.checked_div(divisor)?,
+            denominator: denominator.checked_div(divisor)?,
+        })
+    }
 
-    for line in input.lines() {
-        let history = generate_history(line);
+    fn integer(value: i128) -> Self {
+        Self {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    fn checked_add(self, other: Rational) -> Option<Rational> {
+        Rational::new(
+            self.numerator
+                .checked_mul(other.denominator)?
+                .checked_add(other.numerator.checked_mul(self.denominator)?)?,
+            self.denominator.checked_mul(other.denominator)?,
+        )
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Polynomial {
+    // leading entry of each non-zero row of the difference table
+    differences: Vec<i128>,
+}
+
+impl Polynomial {
+    fn fit(history: &[i64]) -> Result<Self> {
+        let overflow = || anyhow!("fitting {:?} overflows an i128", history);
+        let mut differences = Vec::new();
+        let mut row: Vec<i128> = history.iter().map(|x| *x as i128).collect();
 
-        sum += history
+        while row.is_empty() || row.iter().any(|x| *x != 0) {
+            if row.len() < 2 {
+                bail!(
+                    "differences of {:?} never reach zero before running out of values",
+                    history
+                );
+            }
+
+            differences.push(row[0]);
+            row = row
+                .windows(2)
+                .map(|w| w[1].checked_sub(w[0]).ok_or_else(overflow))
+                .collect::<Result<Vec<i128>>>()?;
+        }
+
+        Ok(Self { differences })
+    }
+
+    fn degree(&self) -> usize {
+        self.differences.len().saturating_sub(1)
+    }
+
+    // coefficients of x^0, x^1, ... where x is the index into the history
+    fn coefficients(&self) -> Result<Vec<Rational>> {
+        let overflow = || {
+            anyhow!(
+                "coefficients of a degree {} fit overflow an i128",
+                self.degree()
+            )
+        };
+        let differences = &self.differences;
+
+        // newton's forward form: p(x) = sum of differences[k] * (x choose k),
+        // expanded by multiplying out the falling factorial x (x - 1) ... (x - k + 1)
+        let mut coefficients = vec![Rational::integer(0); differences.len().max(1)];
+        let mut falling: Vec<i128> = vec![1];
+        let mut factorial: i128 = 1;
+        for (k, difference) in differences.iter().enumerate() {
+            for (power, coefficient) in falling.iter().enumerate() {
+                coefficients[power] = difference
+                    .checked_mul(*coefficient)
+                    .and_then(|numerator| Rational::new(numerator, factorial))
+                    .and_then(|term| coefficients[power].checked_add(term))
+                    .ok_or_else(overflow)?;
+            }
+
+            let mut next: Vec<i128> = vec![0; falling.len() + 1];
+            for (power, coefficient) in falling.iter().enumerate() {
+                next[power + 1] = next[power + 1]
+                    .checked_add(*coefficient)
+                    .ok_or_else(overflow)?;
+                next[power] = coefficient
+                    .checked_mul(k as i128)
+                    .and_then(|term| next[power].checked_sub(term))
+                    .ok_or_else(overflow)?;
+            }
+            falling = next;
+            factorial = factorial.checked_mul(k as i128 + 1).ok_or_else(overflow)?;
+        }
+
+        Ok(coefficients)
+    }
+
+    fn expanded(&self) -> Result<String> {
+        let terms: Vec<String> = self
+            .coefficients()?
             .iter()
-            .rev()
-            .skip(1)
-            .fold(0, |acc, x| acc + x.last().unwrap());
+            .enumerate()
+            .map(|(power, coefficient)| match power {
+                0 => coefficient.to_string(),
+                1 => format!("{} x", coefficient),
+                _ => format!("{} x^{}", coefficient, power),
+            })
+            .collect();
+
+        Ok(terms.join(" + "))
     }
 
-    sum
+    fn value_at(&self, index: i64) -> Result<i128> {
+        // (x choose k + 1) = (x choose k) * (x - k) / (k + 1) divides exactly
+        // for every integer x, so the walk never leaves the integers
+        let x = index as i128;
+        let mut binomial: i128 = 1;
+        let mut value: i128 = 0;
+        for (k, difference) in self.differences.iter().enumerate() {
+            value = difference
+                .checked_mul(binomial)
+                .and_then(|term| value.checked_add(term))
+                .ok_or_else(|| anyhow!("value at index {} overflows an i128", index))?;
+
+            // the binomial after the last difference is never used
+            if k + 1 == self.differences.len() {
+                break;
+            }
+            binomial = binomial
+                .checked_mul(x - k as i128)
+                .ok_or_else(|| anyhow!("value at index {} overflows an i128", index))?
+                / (k as i128 + 1);
+        }
+
+        Ok(value)
+    }
 }
 
-fn part_2(input: &str) -> i64 {
-    let mut sum = 0;
+fn parse_history(line: &str) -> Result<Vec<i64>> {
+    Ok(line
+        .split_whitespace()
+        .map(|x| x.parse())
+        .collect::<Result<Vec<i64>, _>>()?)
+}
+
+fn sum_at(input: &str, index: impl Fn(usize) -> i64) -> Result<i128> {
+    let mut sum: i128 = 0;
 
     for line in input.lines() {
-        let history = generate_history(line);
+        let history = parse_history(line)?;
+        let value = Polynomial::fit(&history)?.value_at(index(history.len()))?;
+        sum = sum
+            .checked_add(value)
+            .ok_or_else(|| anyhow!("sum of predicted values overflows an i128"))?;
+    }
+
+    Ok(sum)
+}
+
+// sign and magnitude with 64-bit limbs, least significant first, supporting
+// just what the binomial weights need
+#[derive(Debug, PartialEq, Clone)]
+struct BigInt {
+    negative: bool,
+    limbs: Vec<u64>,
+}
+
+impl BigInt {
+    fn from_i128(value: i128) -> Self {
+        let magnitude = value.unsigned_abs();
+        Self {
+            negative: value < 0,
+            limbs: vec![magnitude as u64, (magnitude >> 64) as u64],
+        }
+        .trimmed()
+    }
 
-        sum += history
+    fn trimmed(mut self) -> Self {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        if self.limbs.is_empty() {
+            self.negative = false;
+        }
+        self
+    }
+
+    fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn mul_small(&self, factor: u64) -> Self {
+        let mut carry: u128 = 0;
+        let mut limbs = Vec::with_capacity(self.limbs.len() + 1);
+        for limb in &self.limbs {
+            let product = *limb as u128 * factor as u128 + carry;
+            limbs.push(product as u64);
+            carry = product >> 64;
+        }
+        limbs.push(carry as u64);
+
+        Self {
+            negative: self.negative,
+            limbs,
+        }
+        .trimmed()
+    }
+
+    fn div_small(&self, divisor: u64) -> Self {
+        let mut remainder: u128 = 0;
+        let mut limbs = vec![0; self.limbs.len()];
+        for (limb, quotient) in self.limbs.iter().zip(limbs.iter_mut()).rev() {
+            let current = (remainder << 64) | *limb as u128;
+            *quotient = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+
+        Self {
+            negative: self.negative,
+            limbs,
+        }
+        .trimmed()
+    }
+
+    // self * value, negated when negate is set
+    fn times(&self, value: i64, negate: bool) -> Self {
+        let mut product = self.mul_small(value.unsigned_abs());
+        if !product.is_zero() {
+            product.negative = product.negative != ((value < 0) != negate);
+        }
+        product
+    }
+
+    fn add(&self, other: &BigInt) -> Self {
+        let longer = self.limbs.len().max(other.limbs.len());
+        let limb = |big: &BigInt, i: usize| big.limbs.get(i).copied().unwrap_or(0);
+
+        if self.negative == other.negative {
+            let mut carry = false;
+            let mut limbs = Vec::with_capacity(longer + 1);
+            for i in 0..longer {
+                let (sum, overflow_a) = limb(self, i).overflowing_add(limb(other, i));
+                let (sum, overflow_b) = sum.overflowing_add(carry as u64);
+                limbs.push(sum);
+                carry = overflow_a || overflow_b;
+            }
+            limbs.push(carry as u64);
+
+            return Self {
+                negative: self.negative,
+                limbs,
+            }
+            .trimmed();
+        }
+
+        // opposite signs: subtract the smaller magnitude from the larger
+        let magnitude = |big: &BigInt| {
+            (
+                big.limbs.len(),
+                big.limbs.iter().rev().copied().collect::<Vec<u64>>(),
+            )
+        };
+        let (larger, smaller) = if magnitude(self) >= magnitude(other) {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut borrow = false;
+        let mut limbs = Vec::with_capacity(longer);
+        for i in 0..longer {
+            let (difference, borrow_a) = limb(larger, i).overflowing_sub(limb(smaller, i));
+            let (difference, borrow_b) = difference.overflowing_sub(borrow as u64);
+            limbs.push(difference);
+            borrow = borrow_a || borrow_b;
+        }
+
+        Self {
+            negative: larger.negative,
+            limbs,
+        }
+        .trimmed()
+    }
+
+    fn to_i128(&self) -> Option<i128> {
+        if self.limbs.len() > 2 {
+            return None;
+        }
+        let magnitude = self
+            .limbs
             .iter()
             .rev()
-            .skip(1)
-            .fold(0, |acc, x| x.first().unwrap() - acc);
-    }
+            .fold(0u128, |acc, limb| (acc << 64) | *limb as u128);
 
-    sum
+        if !self.negative {
+            i128::try_from(magnitude).ok()
+        } else if magnitude <= i128::MIN.unsigned_abs() {
+            Some((magnitude as i128).wrapping_neg())
+        } else {
+            None
+        }
+    }
 }
 
-fn generate_history(input: &str) -> Vec<Vec<i64>> {
-    let mut history: Vec<Vec<i64>> = Vec::new();
+fn extrapolate(history: &[i64]) -> Result<(i128, i128)> {
+    // with n values and the nth difference zero, y[n] and y[-1] are sums of
+    // y[i] weighted by alternating binomial coefficients, and the (n - 1)th
+    // difference must already be zero for the table to have bottomed out.
+    // the weights outgrow any fixed width long before the values do, so
+    // they are kept as big integers
+    let n = history.len() as u64;
+    if n == 0 {
+        bail!("empty history");
+    }
 
-    let mut history_step: Vec<i64> = input
-        .split_whitespace()
-        .map(|x| x.parse().unwrap())
-        .collect();
+    let mut next = BigInt::from_i128(0);
+    let mut previous = BigInt::from_i128(0);
+    let mut last_difference = BigInt::from_i128(0);
+    // (n choose i), (n choose i + 1) and (n - 1 choose i)
+    let mut choose_n = BigInt::from_i128(1);
+    let mut choose_n_next = BigInt::from_i128(n as i128);
+    let mut choose_n_less = BigInt::from_i128(1);
+
+    for (i, value) in history.iter().enumerate() {
+        let i = i as u64;
+        let odd = (n - 1 - i) % 2 == 1;
+
+        next = next.add(&choose_n.times(*value, odd));
+        previous = previous.add(&choose_n_next.times(*value, i % 2 == 1));
+        last_difference = last_difference.add(&choose_n_less.times(*value, odd));
+
+        choose_n = choose_n.mul_small(n - i).div_small(i + 1);
+        choose_n_next = choose_n_next.mul_small(n - i - 1).div_small(i + 2);
+        choose_n_less = choose_n_less.mul_small(n - 1 - i).div_small(i + 1);
+    }
 
-    history.push(history_step.clone());
+    if !last_difference.is_zero() {
+        bail!(
+            "differences of {:?} never reach zero before running out of values",
+            history
+        );
+    }
+
+    let to_i128 = |value: BigInt| {
+        value
+            .to_i128()
+            .ok_or_else(|| anyhow!("extrapolating {:?} overflows an i128", history))
+    };
+    Ok((to_i128(next)?, to_i128(previous)?))
+}
+
+fn sum_extrapolations(reader: impl BufRead) -> Result<(i128, i128)> {
+    let mut next_sum: i128 = 0;
+    let mut previous_sum: i128 = 0;
 
-    while !history_step.iter().all(|x| x == &0) {
-        history_step = history_step.windows(2).map(|w| w[1] - w[0]).collect();
-        history.push(history_step.clone());
+    for line in reader.lines() {
+        let (next, previous) = extrapolate(&parse_history(&line?)?)?;
+        next_sum = next_sum
+            .checked_add(next)
+            .ok_or_else(|| anyhow!("sum of next values overflows an i128"))?;
+        previous_sum = previous_sum
+            .checked_add(previous)
+            .ok_or_else(|| anyhow!("sum of previous values overflows an i128"))?;
     }
 
-    history
+    Ok((next_sum, previous_sum))
+}
+
+fn part_1(input: &str) -> Result<i128> {
+    Ok(sum_extrapolations(input.as_bytes())?.0)
+}
+
+fn part_2(input: &str) -> Result<i128> {
+    Ok(sum_extrapolations(input.as_bytes())?.1)
 }
 
 #[cfg(test)]
@@ -72,7 +464,7 @@ mod tests {
     fn test_part_1() -> Result<()> {
         let input = fs::read_to_string("input/test/day9.txt")?;
 
-        let result = part_1(&input);
+        let result = part_1(&input)?;
         assert_eq!(result, 114);
 
         Ok(())
@@ -82,9 +474,104 @@ mod tests {
     fn test_part_2() -> Result<()> {
         let input = fs::read_to_string("input/test/day9.txt")?;
 
-        let result = part_2(&input);
+        let result = part_2(&input)?;
         assert_eq!(result, 2);
 
         Ok(())
     }
+
+    #[test]
+    fn test_polynomial() -> Result<()> {
+        let polynomial = Polynomial::fit(&[1, 3, 6, 10, 15, 21])?;
+
+        assert_eq!(polynomial.degree(), 2);
+        assert_eq!(
+            polynomial.coefficients()?,
+            vec![
+                Rational::integer(1),
+                Rational::new(3, 2).unwrap(),
+                Rational::new(1, 2).unwrap()
+            ]
+        );
+        assert_eq!(polynomial.expanded()?, "1 + 3/2 x + 1/2 x^2");
+        assert_eq!(polynomial.value_at(6)?, 28);
+        assert_eq!(polynomial.value_at(-1)?, 0);
+        assert_eq!(polynomial.value_at(-4)?, 3);
+        assert_eq!(polynomial.value_at(100)?, 5151);
+
+        assert_eq!(Polynomial::fit(&[0, 0])?.expanded()?, "0");
+        assert_eq!(Polynomial::fit(&[7, 7])?.value_at(-10)?, 7);
+        assert!(Polynomial::fit(&[5]).is_err());
+        assert!(Polynomial::fit(&[1, 2, 4]).is_err());
+        assert!(Polynomial::fit(&[]).is_err());
+        assert_eq!(
+            Polynomial::fit(&[0, 1, 4, 9])?.value_at(i64::MAX)?,
+            i64::MAX as i128 * i64::MAX as i128
+        );
+        assert!(Polynomial::fit(&[0, 0, 0, 1, 4])?
+            .value_at(i64::MAX)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extrapolate() -> Result<()> {
+        assert_eq!(extrapolate(&[10, 13, 16, 21, 30, 45])?, (68, 5));
+        assert_eq!(extrapolate(&[0])?, (0, 0));
+        assert!(extrapolate(&[1, 2, 4]).is_err());
+        assert!(extrapolate(&[]).is_err());
+
+        // agrees with the polynomial fit on every line
+        let input = fs::read_to_string("input/test/day9.txt")?;
+        for line in input.lines() {
+            let history = parse_history(line)?;
+            let polynomial = Polynomial::fit(&history)?;
+            assert_eq!(
+                extrapolate(&history)?,
+                (
+                    polynomial.value_at(history.len() as i64)?,
+                    polynomial.value_at(-1)?
+                )
+            );
+        }
+
+        // (x choose 39) needs 39! for its coefficients, but not to predict values
+        let choose_39: Vec<i64> = (0..41)
+            .map(|i| match i {
+                39 => 1,
+                40 => 40,
+                _ => 0,
+            })
+            .collect();
+        let polynomial = Polynomial::fit(&choose_39)?;
+        assert_eq!(polynomial.degree(), 39);
+        assert!(polynomial.coefficients().is_err());
+        assert_eq!(polynomial.value_at(41)?, 820);
+        assert_eq!(polynomial.value_at(-1)?, -1);
+        assert_eq!(extrapolate(&choose_39)?, (820, -1));
+
+        let alternating: Vec<i64> = (0..80)
+            .map(|i| if i % 2 == 0 { i64::MIN } else { i64::MAX })
+            .collect();
+        assert!(Polynomial::fit(&alternating).is_err());
+        assert!(extrapolate(&alternating).is_err());
+
+        let big = BigInt::from_i128;
+        assert_eq!(big(5).add(&big(-7)), big(-2));
+        assert_eq!(big(-5).add(&big(5)), big(0));
+        assert_eq!(big(i128::MIN).to_i128(), Some(i128::MIN));
+        assert_eq!(big(i128::MAX).add(&big(1)).to_i128(), None);
+        assert_eq!(big(1 << 100).mul_small(6).div_small(4), big(3 << 99));
+
+        // the weights for 300 values are far wider than an i128
+        let long: Vec<i64> = (0..300).collect();
+        assert_eq!(extrapolate(&long)?, (300, -1));
+        assert_eq!(
+            extrapolate(&[i64::MAX, i64::MAX])?,
+            (i64::MAX as i128, i64::MAX as i128)
+        );
+
+        Ok(())
+    }
 }